@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::{env, expansion, variables, Shell};
+use crate::{env, error, expansion, variables, Shell};
 use brush_parser::ast;
 
 /// Represents an error that occurs during evaluation of an arithmetic expression.
@@ -37,6 +37,10 @@ pub enum EvalError {
     /// Failed to trace an arithmetic expression.
     #[error("failed tracing expression")]
     TraceError,
+
+    /// An unset variable was referenced while `set -u` was in effect.
+    #[error("{0}: unbound variable")]
+    UnboundVariable(String),
 }
 
 /// Trait implemented by arithmetic expressions that can be evaluated.
@@ -57,7 +61,10 @@ impl ExpandAndEvaluate for ast::UnexpandedArithmeticExpr {
         // Per documentation, first shell-expand it.
         let expanded_self = expansion::basic_expand_str_without_tilde(shell, self.value.as_str())
             .await
-            .map_err(|_e| EvalError::FailedToExpandExpression)?;
+            .map_err(|e| match e {
+                error::Error::UnboundVariable(name) => EvalError::UnboundVariable(name),
+                _ => EvalError::FailedToExpandExpression,
+            })?;
 
         // Now parse.
         let expr = brush_parser::arithmetic::parse(&expanded_self)
@@ -67,6 +74,7 @@ impl ExpandAndEvaluate for ast::UnexpandedArithmeticExpr {
         if trace_if_needed && shell.options.print_commands_and_arguments {
             shell
                 .trace_command(std::format!("(( {expr} ))"))
+                .await
                 .map_err(|_err| EvalError::TraceError)?;
         }
 
@@ -132,11 +140,12 @@ impl Evaluatable for ast::ArithmeticExpr {
 }
 
 async fn deref_lvalue(shell: &mut Shell, lvalue: &ast::ArithmeticTarget) -> Result<i64, EvalError> {
-    let value_str: Cow<'_, str> = match lvalue {
+    let value_str: Option<Cow<'_, str>> = match lvalue {
         ast::ArithmeticTarget::Variable(name) => shell
             .env
             .get(name)
-            .map_or_else(|| Cow::Borrowed(""), |(_, v)| v.value().to_cow_string()),
+            .filter(|(_, v)| !matches!(v.value(), variables::ShellValue::Unset(_)))
+            .map(|(_, v)| v.value().to_cow_string()),
         ast::ArithmeticTarget::ArrayElement(name, index_expr) => {
             let index_str = index_expr.eval(shell).await?.to_string();
 
@@ -145,10 +154,17 @@ async fn deref_lvalue(shell: &mut Shell, lvalue: &ast::ArithmeticTarget) -> Resu
                 .get(name)
                 .map_or_else(|| Ok(None), |(_, v)| v.value().get_at(index_str.as_str()))
                 .map_err(|_err| EvalError::FailedToAccessArray)?
-                .unwrap_or(Cow::Borrowed(""))
         }
     };
 
+    let Some(value_str) = value_str else {
+        if shell.options.treat_unset_variables_as_error {
+            return Err(EvalError::UnboundVariable(lvalue.to_string()));
+        }
+
+        return Ok(0);
+    };
+
     let value: i64 = value_str.parse().unwrap_or(0);
     Ok(value)
 }
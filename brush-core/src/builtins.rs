@@ -38,6 +38,7 @@ mod jobs;
 #[cfg(unix)]
 mod kill;
 mod let_;
+mod mapfile;
 mod popd;
 mod printf;
 mod pushd;
@@ -48,10 +49,13 @@ mod set;
 mod shift;
 mod shopt;
 mod test;
+mod times;
 mod trap;
 mod true_;
 mod type_;
 #[cfg(unix)]
+mod ulimit;
+#[cfg(unix)]
 mod umask;
 mod unalias;
 mod unimp;
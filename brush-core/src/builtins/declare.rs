@@ -125,15 +125,6 @@ impl builtins::Command for DeclareCommand {
             _ => DeclareVerb::Declare,
         };
 
-        // TODO: implement declare -I
-        if self.locals_inherit_from_prev_scope {
-            writeln!(
-                context.stderr(),
-                "UNIMPLEMENTED: declare -I: locals inherit from previous scope"
-            )?;
-            return Ok(builtins::ExitCode::Unimplemented);
-        }
-
         let mut result = builtins::ExitCode::Success;
         if !self.declarations.is_empty() {
             for declaration in &self.declarations {
@@ -191,6 +182,15 @@ impl DeclareCommand {
                 if self.function_names_only {
                     if self.print {
                         writeln!(context.stdout(), "declare -f {name}")?;
+                    } else if context.shell.options.enable_debugger {
+                        // With extdebug enabled, also report where the function was defined.
+                        let definition = &func_registration.definition;
+                        writeln!(
+                            context.stdout(),
+                            "{name} {} {}",
+                            definition.location.line,
+                            definition.source
+                        )?;
                     } else {
                         writeln!(context.stdout(), "{name}")?;
                     }
@@ -202,7 +202,11 @@ impl DeclareCommand {
                 writeln!(context.stderr(), "declare: {name}: not found")?;
                 Ok(false)
             }
-        } else if let Some(variable) = context.shell.env.get_using_policy(name, lookup) {
+        } else if let Some(variable) = context
+            .shell
+            .env
+            .get_using_policy_ignoring_nameref(name, lookup)
+        {
             let mut cs = variable.get_attribute_flags();
             if cs.is_empty() {
                 cs.push('-');
@@ -229,6 +233,21 @@ impl DeclareCommand {
         }
     }
 
+    fn try_set_function_traced(
+        context: &mut crate::commands::ExecutionContext<'_>,
+        declaration: &commands::CommandArg,
+        traced: bool,
+    ) -> Result<bool, error::Error> {
+        let name = declaration.to_string();
+        if let Some(registration) = context.shell.funcs.get_mut(name.as_str()) {
+            registration.traced = traced;
+            Ok(true)
+        } else {
+            writeln!(context.stderr(), "declare: {name}: not found")?;
+            Ok(false)
+        }
+    }
+
     fn process_declaration(
         &self,
         context: &mut crate::commands::ExecutionContext<'_>,
@@ -239,6 +258,11 @@ impl DeclareCommand {
             || (context.shell.in_function() && !self.create_global);
 
         if self.function_names_or_defs_only || self.function_names_only {
+            // Update the function's trace attribute, if requested; otherwise, display it.
+            if let Some(value) = self.make_traced.to_bool() {
+                return Self::try_set_function_traced(context, declaration, value);
+            }
+
             return self.try_display_declaration(context, declaration, verb);
         }
 
@@ -253,11 +277,41 @@ impl DeclareCommand {
             EnvironmentLookup::Anywhere
         };
 
+        // Unless we've been asked to update the name reference attribute, we operate on
+        // the variable that a name reference refers to.
+        let (name, lookup) = if let Some(make_nameref) = self.make_nameref.to_bool() {
+            let self_reference = match &initial_value {
+                Some(ShellValueLiteral::Scalar(target)) => *target == name,
+                _ => false,
+            };
+
+            if make_nameref && self_reference {
+                writeln!(
+                    context.stderr(),
+                    "declare: {name}: nameref variable self references not allowed"
+                )?;
+                return Ok(false);
+            }
+
+            (name, lookup)
+        } else if let Some(target) = context
+            .shell
+            .env
+            .resolve_nameref_using_policy(name.as_str(), lookup)?
+        {
+            (target, EnvironmentLookup::Anywhere)
+        } else {
+            (name, lookup)
+        };
+
+        // With allexport enabled, assigning a value also exports the variable.
+        let auto_export = context.shell.options.export_variables_on_modification;
+
         // Look up the variable.
         if let Some(var) = context
             .shell
             .env
-            .get_mut_using_policy(name.as_str(), lookup)
+            .get_mut_using_policy_ignoring_nameref(name.as_str(), lookup)
         {
             if self.make_associative_array.is_some() {
                 var.convert_to_associative_array()?;
@@ -271,6 +325,9 @@ impl DeclareCommand {
             if let Some(initial_value) = initial_value {
                 // We append if the declaration included an explicit index.
                 var.assign(initial_value, assigned_index.is_some())?;
+                if auto_export {
+                    var.export();
+                }
             }
 
             self.apply_attributes_after_update(var, verb)?;
@@ -285,12 +342,35 @@ impl DeclareCommand {
                 ShellValueUnsetType::Untyped
             };
 
-            let mut var = ShellVariable::new(ShellValue::Unset(unset_type));
+            // New locals may start out as a copy of the variable they shadow.
+            let inherit = create_var_local
+                && (self.locals_inherit_from_prev_scope
+                    || context.shell.options.local_vars_inherit_value_and_attrs);
+
+            let mut var = match context.shell.env.get_ignoring_nameref(name.as_str()) {
+                Some((_, shadowed_var)) if inherit => {
+                    let mut var = shadowed_var.clone();
+                    var.unset_treat_as_nameref();
+
+                    if self.make_associative_array.is_some() {
+                        var.convert_to_associative_array()?;
+                    }
+                    if self.make_indexed_array.is_some() {
+                        var.convert_to_indexed_array()?;
+                    }
+
+                    var
+                }
+                _ => ShellVariable::new(ShellValue::Unset(unset_type)),
+            };
 
             self.apply_attributes_before_update(&mut var)?;
 
             if let Some(initial_value) = initial_value {
                 var.assign(initial_value, false)?;
+                if auto_export {
+                    var.export();
+                }
             }
 
             self.apply_attributes_after_update(&mut var, verb)?;
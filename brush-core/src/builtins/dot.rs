@@ -2,7 +2,7 @@ use std::path::Path;
 
 use clap::Parser;
 
-use crate::{builtins, commands};
+use crate::{builtins, commands, traps};
 
 /// Evalute the provided script in the current shell environment.
 #[derive(Debug, Parser)]
@@ -33,6 +33,19 @@ impl builtins::Command for DotCommand {
             )
             .await?;
 
+        // Run the RETURN trap, if one is registered.
+        if let Some(trap_result) = context
+            .shell
+            .run_registered_trap_handler(traps::TrapSignal::Return, &params)
+            .await?
+        {
+            return Ok(trap_result.into());
+        }
+
+        if result.exit_shell {
+            return Ok(builtins::ExitCode::ExitShell(result.exit_code));
+        }
+
         if result.exit_code != 0 {
             return Ok(builtins::ExitCode::Custom(result.exit_code));
         }
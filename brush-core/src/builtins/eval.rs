@@ -20,9 +20,12 @@ impl builtins::Command for EvalCommand {
             tracing::debug!("Applying eval to: {:?}", args_concatenated);
 
             let params = context.params.clone();
-            let exec_result = context.shell.run_string(args_concatenated, &params).await?;
+            let exec_result = context
+                .shell
+                .run_nested_string(args_concatenated, &params)
+                .await?;
 
-            Ok(builtins::ExitCode::Custom(exec_result.exit_code))
+            Ok(exec_result.into())
         } else {
             Ok(builtins::ExitCode::Success)
         }
@@ -26,9 +26,21 @@ pub(crate) struct ExecCommand {
 impl builtins::Command for ExecCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<builtins::ExitCode, crate::error::Error> {
+        // With no command to exec, the command's redirections persist in the shell.
         if self.args.is_empty() {
+            for fd_num in &context.redirected_fds {
+                let file = context
+                    .params
+                    .open_files
+                    .files
+                    .get(fd_num)
+                    .map(|f| f.try_dup())
+                    .transpose()?;
+                context.shell.open_files.set_persistent(*fd_num, file);
+            }
+
             return Ok(builtins::ExitCode::Success);
         }
 
@@ -219,10 +219,7 @@ pub(crate) fn get_default_builtins(
         "readonly".into(),
         special_decl_builtin::<declare::DeclareCommand>(),
     );
-    m.insert(
-        "times".into(),
-        special_builtin::<unimp::UnimplementedCommand>(),
-    );
+    m.insert("times".into(), special_builtin::<times::TimesCommand>());
 
     //
     // Non-special builtins
@@ -246,13 +243,14 @@ pub(crate) fn get_default_builtins(
     m.insert("true".into(), builtin::<true_::TrueCommand>());
     m.insert("type".into(), builtin::<type_::TypeCommand>());
     #[cfg(unix)]
+    m.insert("ulimit".into(), builtin::<ulimit::ULimitCommand>());
+    #[cfg(unix)]
     m.insert("umask".into(), builtin::<umask::UmaskCommand>());
     m.insert("unalias".into(), builtin::<unalias::UnaliasCommand>());
     m.insert("wait".into(), builtin::<wait::WaitCommand>());
 
     // TODO: Unimplemented non-special builtins
     m.insert("fc".into(), builtin::<unimp::UnimplementedCommand>());
-    m.insert("ulimit".into(), builtin::<unimp::UnimplementedCommand>());
 
     if !options.sh_mode {
         m.insert("builtin".into(), builtin::<builtin_::BuiltinCommand>());
@@ -260,7 +258,9 @@ pub(crate) fn get_default_builtins(
         m.insert("echo".into(), builtin::<echo::EchoCommand>());
         m.insert("enable".into(), builtin::<enable::EnableCommand>());
         m.insert("let".into(), builtin::<let_::LetCommand>());
+        m.insert("mapfile".into(), builtin::<mapfile::MapFileCommand>());
         m.insert("printf".into(), builtin::<printf::PrintfCommand>());
+        m.insert("readarray".into(), builtin::<mapfile::MapFileCommand>());
         m.insert("shopt".into(), builtin::<shopt::ShoptCommand>());
         m.insert("source".into(), special_builtin::<dot::DotCommand>());
         m.insert("test".into(), builtin::<test::TestCommand>());
@@ -283,8 +283,6 @@ pub(crate) fn get_default_builtins(
         m.insert("disown".into(), builtin::<unimp::UnimplementedCommand>());
         m.insert("history".into(), builtin::<unimp::UnimplementedCommand>());
         m.insert("logout".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("mapfile".into(), builtin::<unimp::UnimplementedCommand>());
-        m.insert("readarray".into(), builtin::<unimp::UnimplementedCommand>());
         m.insert("suspend".into(), builtin::<unimp::UnimplementedCommand>());
     }
 
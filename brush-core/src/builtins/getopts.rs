@@ -157,10 +157,9 @@ impl builtins::Command for GetOptsCommand {
                     // We have more to go in this argument, so update the internal char index
                     // and request that OPTIND not be updated.
                     new_optind = next_index;
-                    context.shell.env.update_or_add(
+                    context.shell.env.update_or_add_shell_managed(
                         VAR_GETOPTS_NEXT_CHAR_INDEX,
                         variables::ShellValueLiteral::Scalar((next_char_index + 1).to_string()),
-                        |_| Ok(()),
                         crate::env::EnvironmentLookup::Anywhere,
                         crate::env::EnvironmentScope::Global,
                     )?;
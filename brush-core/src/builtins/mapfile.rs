@@ -0,0 +1,166 @@
+use clap::Parser;
+use std::io::{BufRead, Write};
+
+use crate::{builtins, commands, env, error, escape, expansion, variables};
+
+/// Read lines from standard input into an indexed array variable.
+#[derive(Parser)]
+pub(crate) struct MapFileCommand {
+    /// Delimiter to use to terminate lines instead of a newline character; an
+    /// empty string selects the NUL character.
+    #[clap(short = 'd')]
+    delimiter: Option<String>,
+
+    /// Maximum number of lines to copy; 0 indicates no limit.
+    #[clap(short = 'n', default_value_t = 0)]
+    max_count: usize,
+
+    /// Index in the array at which to start assigning lines. If specified,
+    /// the array is not cleared before assignment.
+    #[clap(short = 'O')]
+    origin: Option<u64>,
+
+    /// Number of initial lines to discard.
+    #[clap(short = 's', default_value_t = 0)]
+    skip_count: usize,
+
+    /// Remove the trailing delimiter from each line read.
+    #[clap(short = 't')]
+    remove_delimiter: bool,
+
+    /// File descriptor to read from instead of stdin.
+    #[clap(short = 'u', name = "FD")]
+    fd_num_to_read: Option<u32>,
+
+    /// Command to evaluate each time `quantum` lines are read.
+    #[clap(short = 'C')]
+    callback: Option<String>,
+
+    /// Number of lines to read between each evaluation of the callback.
+    #[clap(short = 'c', name = "QUANTUM", default_value_t = 5000)]
+    callback_quantum: usize,
+
+    /// Name of the array variable to receive lines.
+    #[clap(default_value = "MAPFILE")]
+    array_variable: String,
+}
+
+impl builtins::Command for MapFileCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        if self.callback_quantum == 0 {
+            writeln!(
+                context.stderr(),
+                "{}: 0: invalid callback quantum",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        if !expansion::valid_variable_name(self.array_variable.as_str()) {
+            writeln!(
+                context.stderr(),
+                "{}: `{}': not a valid identifier",
+                context.command_name,
+                self.array_variable
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        if let Some((_, var)) = context.shell.env.get(&self.array_variable) {
+            if matches!(var.value(), variables::ShellValue::AssociativeArray(_)) {
+                writeln!(
+                    context.stderr(),
+                    "{}: {}: not an indexed array",
+                    context.command_name,
+                    self.array_variable
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        }
+
+        // Find the input stream to use.
+        let input_file = if let Some(fd_num) = self.fd_num_to_read {
+            context
+                .fd(fd_num)
+                .ok_or_else(|| error::Error::BadFileDescriptor(fd_num))?
+        } else {
+            context.stdin()
+        };
+
+        // If we've been asked to stop after a given number of lines, then make sure we don't
+        // read ahead any further than needed; anything remaining is left for later readers.
+        let mut reader = if self.max_count > 0 {
+            std::io::BufReader::with_capacity(1, input_file)
+        } else {
+            std::io::BufReader::new(input_file)
+        };
+
+        // Unless an origin was specified, the array is cleared first.
+        if self.origin.is_none() {
+            context.shell.env.update_or_add(
+                self.array_variable.as_str(),
+                variables::ShellValueLiteral::Array(variables::ArrayLiteral(vec![])),
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+        }
+
+        let delimiter = match &self.delimiter {
+            Some(delimiter) => delimiter.bytes().next().unwrap_or(b'\0'),
+            None => b'\n',
+        };
+
+        let mut index = self.origin.unwrap_or(0);
+        let mut skipped_count = 0;
+        let mut copied_count = 0;
+        let mut buffer = vec![];
+
+        while self.max_count == 0 || copied_count < self.max_count {
+            buffer.clear();
+            if reader.read_until(delimiter, &mut buffer)? == 0 {
+                break;
+            }
+
+            if skipped_count < self.skip_count {
+                skipped_count += 1;
+                continue;
+            }
+
+            if self.remove_delimiter && buffer.last() == Some(&delimiter) {
+                buffer.pop();
+            }
+
+            let line = String::from_utf8_lossy(buffer.as_slice()).into_owned();
+            copied_count += 1;
+
+            // The callback gets invoked before the line is assigned.
+            if let Some(callback) = &self.callback {
+                if copied_count % self.callback_quantum == 0 {
+                    let command = std::format!(
+                        "{callback} {index} {}",
+                        escape::quote_if_needed(&line, escape::QuoteMode::BackslashEscape)
+                    );
+                    let params = context.params.clone();
+                    context.shell.run_nested_string(command, &params).await?;
+                }
+            }
+
+            context.shell.env.update_or_add_array_element(
+                self.array_variable.as_str(),
+                index.to_string(),
+                line,
+                |_| Ok(()),
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
+            )?;
+
+            index += 1;
+        }
+
+        Ok(builtins::ExitCode::Success)
+    }
+}
@@ -1,7 +1,14 @@
 use clap::Parser;
-use std::io::Write;
+use std::{fmt::Write as _, io::Write, iter::Peekable, str::Chars};
 
-use crate::{builtins, commands, escape, expansion};
+use crate::{builtins, commands, error, escape, expansion, openfiles};
+
+/// Largest field width or precision honored, matching bash's use of a C `int`.
+const MAX_FORMAT_COUNT: usize = 0x7fff_ffff;
+
+/// Number of digits after the decimal point beyond which an `f64` has no non-zero digits;
+/// any further digits requested are zeros.
+const MAX_EXACT_FLOAT_DIGITS: usize = 1100;
 
 /// Format a string.
 #[derive(Parser)]
@@ -21,71 +28,915 @@ impl builtins::Command for PrintfCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        let result = self.evaluate(&context)?;
+        if let Some(variable_name) = &self.output_variable {
+            if !is_valid_output_variable(&context, variable_name) {
+                writeln!(
+                    context.stderr(),
+                    "{}: `{variable_name}': not a valid identifier",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::InvalidUsage);
+            }
+        }
+
+        let Some((format, args)) = self.format_and_args.split_first() else {
+            return Ok(builtins::ExitCode::InvalidUsage);
+        };
+
+        let pieces = parse_format(format)?;
+
+        let (output, succeeded) = {
+            let mut formatter = Formatter::new(&context, args);
+            formatter.format(pieces.as_slice())?;
+            (formatter.output, !formatter.failed)
+        };
 
         if let Some(variable_name) = &self.output_variable {
-            expansion::assign_to_named_parameter(context.shell, variable_name, result).await?;
+            let value = String::from_utf8_lossy(output.as_slice()).into_owned();
+            expansion::assign_to_named_parameter(context.shell, variable_name, value).await?;
         } else {
-            write!(context.stdout(), "{result}")?;
+            context.stdout().write_all(output.as_slice())?;
             context.stdout().flush()?;
         }
 
-        Ok(builtins::ExitCode::Success)
+        if succeeded {
+            Ok(builtins::ExitCode::Success)
+        } else {
+            Ok(builtins::ExitCode::Custom(1))
+        }
     }
 }
 
-impl PrintfCommand {
-    fn evaluate(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        match self.format_and_args.as_slice() {
-            // Special-case common format string: "%s".
-            [fmt, arg] if fmt == "%s" => Ok(arg.clone()),
-            // Special-case invocation of printf with %q-based format string from bash-completion.
-            // It has hard-coded expectation of backslash-style escaping instead of quoting.
-            [fmt, arg] if fmt == "%q" => Ok(Self::evaluate_format_with_percent_q(None, arg)),
-            [fmt, arg] if fmt == "~%q" => Ok(Self::evaluate_format_with_percent_q(Some("~"), arg)),
-            // Fallback to external command.
-            _ => self.evaluate_via_external_command(context),
+fn is_valid_output_variable(context: &commands::ExecutionContext<'_>, name: &str) -> bool {
+    matches!(
+        brush_parser::word::parse_parameter(name, &context.shell.parser_options()),
+        Ok(brush_parser::word::Parameter::Named(_)
+            | brush_parser::word::Parameter::NamedWithIndex { .. })
+    )
+}
+
+/// A parsed piece of a format string.
+enum FormatPiece {
+    /// Literal bytes, with any escape sequences already expanded.
+    Literal(Vec<u8>),
+    /// A conversion specification.
+    Directive(FormatDirective),
+    /// An invalid conversion specification; processing stops here with the given error.
+    Invalid(String),
+}
+
+struct FormatDirective {
+    flags: FormatFlags,
+    width: Option<FormatCount>,
+    precision: Option<FormatCount>,
+    conversion: Conversion,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FormatFlags {
+    left_justify: bool,
+    always_sign: bool,
+    space_for_sign: bool,
+    alternate_form: bool,
+    zero_pad: bool,
+}
+
+#[derive(Clone, Copy)]
+enum FormatCount {
+    Fixed(usize),
+    FromArg,
+}
+
+enum Conversion {
+    /// `%d` and `%i`
+    SignedInteger,
+    /// `%o`, `%u`, `%x`, and `%X`
+    UnsignedInteger(char),
+    /// `%a`, `%A`, `%e`, `%E`, `%f`, `%F`, `%g`, and `%G`
+    Float(char),
+    /// `%c`
+    Char,
+    /// `%s`
+    String,
+    /// `%b`
+    EscapedString,
+    /// `%q`
+    Quoted,
+    /// `%Q`
+    QuotedAfterPrecision,
+    /// `%(datefmt)T`
+    Time(String),
+}
+
+fn parse_format(format: &str) -> Result<Vec<FormatPiece>, error::Error> {
+    let mut pieces = vec![];
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        if chars.next_if_eq(&'%').is_some() {
+            literal.push('%');
+            continue;
+        }
+
+        if !literal.is_empty() {
+            pieces.push(parse_literal(literal.as_str())?);
+            literal.clear();
+        }
+
+        match parse_directive(&mut chars) {
+            Ok(directive) => pieces.push(FormatPiece::Directive(directive)),
+            Err(message) => {
+                pieces.push(FormatPiece::Invalid(message));
+                return Ok(pieces);
+            }
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(parse_literal(literal.as_str())?);
+    }
+
+    Ok(pieces)
+}
+
+fn parse_literal(literal: &str) -> Result<FormatPiece, error::Error> {
+    let (expanded, _) =
+        escape::expand_backslash_escapes(literal, escape::EscapeExpansionMode::PrintfFormatString)?;
+
+    Ok(FormatPiece::Literal(expanded))
+}
+
+/// Parses a conversion specification, starting just after its introductory '%'. On failure,
+/// returns the error message to display.
+fn parse_directive(chars: &mut Peekable<Chars<'_>>) -> Result<FormatDirective, String> {
+    let mut spec = String::from("%");
+
+    let mut flags = FormatFlags::default();
+    while let Some(c) = chars.next_if(|c| matches!(c, '-' | '+' | ' ' | '#' | '0' | '\'')) {
+        spec.push(c);
+        match c {
+            '-' => flags.left_justify = true,
+            '+' => flags.always_sign = true,
+            ' ' => flags.space_for_sign = true,
+            '#' => flags.alternate_form = true,
+            '0' => flags.zero_pad = true,
+            // N.B. Thousands grouping is a no-op in the locales we support.
+            _ => (),
         }
     }
 
-    fn evaluate_format_with_percent_q(prefix: Option<&str>, arg: &str) -> String {
-        let mut result =
-            escape::quote_if_needed(arg, escape::QuoteMode::BackslashEscape).to_string();
+    let width = parse_count(chars, &mut spec)?;
+
+    let precision = if chars.next_if_eq(&'.').is_some() {
+        spec.push('.');
+        Some(parse_count(chars, &mut spec)?.unwrap_or(FormatCount::Fixed(0)))
+    } else {
+        None
+    };
+
+    // Length modifiers are accepted but have no effect.
+    while let Some(c) = chars.next_if(|c| matches!(c, 'h' | 'j' | 'l' | 'L' | 't' | 'z')) {
+        spec.push(c);
+    }
 
-        if let Some(prefix) = prefix {
-            result.insert_str(0, prefix);
+    let time_format = if chars.next_if_eq(&'(').is_some() {
+        let mut time_format = String::new();
+        loop {
+            match chars.next() {
+                Some(')') => break,
+                Some(c) => time_format.push(c),
+                None => {
+                    return Err(std::format!(
+                        "`{spec}({time_format}': missing time format specification"
+                    ))
+                }
+            }
         }
+        Some(time_format)
+    } else {
+        None
+    };
+
+    let conversion = match (chars.next(), time_format) {
+        (None, _) => return Err(std::format!("`{spec}': missing format character")),
+        (Some('T'), Some(time_format)) => Conversion::Time(time_format),
+        (Some(c), Some(_)) => return Err(std::format!("`{c}': invalid time format specification")),
+        (Some('d' | 'i'), None) => Conversion::SignedInteger,
+        (Some(c @ ('o' | 'u' | 'x' | 'X')), None) => Conversion::UnsignedInteger(c),
+        (Some(c @ ('a' | 'A' | 'e' | 'E' | 'f' | 'F' | 'g' | 'G')), None) => Conversion::Float(c),
+        (Some('c'), None) => Conversion::Char,
+        (Some('s'), None) => Conversion::String,
+        (Some('b'), None) => Conversion::EscapedString,
+        (Some('q'), None) => Conversion::Quoted,
+        (Some('Q'), None) => Conversion::QuotedAfterPrecision,
+        (Some(c), None) => return Err(std::format!("`{c}': invalid format character")),
+    };
+
+    Ok(FormatDirective {
+        flags,
+        width,
+        precision,
+        conversion,
+    })
+}
 
-        result
+/// Parses an optional field width or precision. On failure, returns the error message to
+/// display.
+fn parse_count(
+    chars: &mut Peekable<Chars<'_>>,
+    spec: &mut String,
+) -> Result<Option<FormatCount>, String> {
+    if chars.next_if_eq(&'*').is_some() {
+        spec.push('*');
+        return Ok(Some(FormatCount::FromArg));
     }
 
-    #[allow(clippy::unwrap_in_result)]
-    fn evaluate_via_external_command(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        // TODO: Don't call external printf command.
-        let mut cmd = std::process::Command::new("printf");
-        cmd.env_clear();
-        cmd.args(&self.format_and_args);
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+
+    if digits.is_empty() {
+        return Ok(None);
+    }
+
+    spec.push_str(digits.as_str());
+    match digits.parse() {
+        Ok(count) if count <= MAX_FORMAT_COUNT => Ok(Some(FormatCount::Fixed(count))),
+        _ => Err(std::format!("{digits}: Numerical result out of range")),
+    }
+}
+
+/// Applies a parsed format string to arguments.
+struct Formatter<'a> {
+    args: &'a [String],
+    next_arg_index: usize,
+    output: Vec<u8>,
+    failed: bool,
+    command_name: &'a str,
+    stderr: openfiles::OpenFile,
+    shell_start_time: std::time::SystemTime,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(context: &'a commands::ExecutionContext<'_>, args: &'a [String]) -> Self {
+        Self {
+            args,
+            next_arg_index: 0,
+            output: vec![],
+            failed: false,
+            command_name: context.command_name.as_str(),
+            stderr: context.stderr(),
+            shell_start_time: context.shell.start_time,
+        }
+    }
+
+    fn format(&mut self, pieces: &[FormatPiece]) -> Result<(), error::Error> {
+        // The format string is reused as needed to consume all arguments.
+        loop {
+            let first_arg_index = self.next_arg_index;
+
+            for piece in pieces {
+                match piece {
+                    FormatPiece::Literal(bytes) => self.output.extend_from_slice(bytes),
+                    FormatPiece::Directive(directive) => {
+                        if !self.format_directive(directive)? {
+                            return Ok(());
+                        }
+                    }
+                    FormatPiece::Invalid(message) => {
+                        self.report_error(message)?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if self.next_arg_index == first_arg_index || self.next_arg_index >= self.args.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Applies a single conversion specification; returns whether or not processing should
+    /// continue.
+    #[allow(clippy::too_many_lines)]
+    fn format_directive(&mut self, directive: &FormatDirective) -> Result<bool, error::Error> {
+        let mut flags = directive.flags;
+
+        let width = match directive.width {
+            Some(FormatCount::FromArg) => {
+                let width = self.next_count_arg()?;
+                if width < 0 {
+                    flags.left_justify = true;
+                }
+                usize::try_from(width.unsigned_abs()).unwrap_or(MAX_FORMAT_COUNT)
+            }
+            Some(FormatCount::Fixed(width)) => width,
+            None => 0,
+        };
+
+        // N.B. A negative precision is treated as if it were omitted.
+        let precision = match directive.precision {
+            Some(FormatCount::FromArg) => usize::try_from(self.next_count_arg()?).ok(),
+            Some(FormatCount::Fixed(precision)) => Some(precision),
+            None => None,
+        };
+
+        match &directive.conversion {
+            Conversion::SignedInteger => {
+                let value = self.next_signed_arg()?;
+                let sign = sign_str(value.is_negative(), flags);
+                let digits = apply_integer_precision(value.unsigned_abs().to_string(), precision);
+                self.write_number(sign, digits.as_str(), width, flags, precision.is_none());
+            }
+            Conversion::UnsignedInteger(c) => {
+                let value = self.next_unsigned_arg()?;
+                let (prefix, digits) = match c {
+                    'o' => ("", std::format!("{value:o}")),
+                    'x' => ("0x", std::format!("{value:x}")),
+                    'X' => ("0X", std::format!("{value:X}")),
+                    _ => ("", value.to_string()),
+                };
+
+                let mut digits = apply_integer_precision(digits, precision);
+                let prefix = match c {
+                    'o' if flags.alternate_form && !digits.starts_with('0') => {
+                        digits.insert(0, '0');
+                        ""
+                    }
+                    'x' | 'X' if flags.alternate_form && value != 0 => prefix,
+                    _ => "",
+                };
+
+                self.write_number(prefix, digits.as_str(), width, flags, precision.is_none());
+            }
+            Conversion::Float(c) => {
+                let value = self.next_float_arg()?;
+                let sign = sign_str(value.is_sign_negative(), flags);
+                let value = value.abs();
+
+                let mut formatted = if value.is_infinite() {
+                    String::from("inf")
+                } else if value.is_nan() {
+                    String::from("nan")
+                } else {
+                    match c.to_ascii_lowercase() {
+                        'a' => format_float_hex(value, precision, flags.alternate_form),
+                        'e' => format_float_exponential(
+                            value,
+                            precision.unwrap_or(6),
+                            flags.alternate_form,
+                        ),
+                        'g' => format_float_general(
+                            value,
+                            precision.unwrap_or(6),
+                            flags.alternate_form,
+                        ),
+                        _ => {
+                            format_float_fixed(value, precision.unwrap_or(6), flags.alternate_form)
+                        }
+                    }
+                };
+
+                if c.is_ascii_uppercase() {
+                    formatted.make_ascii_uppercase();
+                }
+
+                self.write_number(sign, formatted.as_str(), width, flags, value.is_finite());
+            }
+            Conversion::Char => {
+                // N.B. This yields a NUL byte if the argument is missing or empty.
+                let c = self
+                    .next_arg()
+                    .and_then(|arg| arg.chars().next())
+                    .unwrap_or('\0');
+                let mut buf = [0; 4];
+                self.write_str(c.encode_utf8(&mut buf).as_bytes(), None, width, flags);
+            }
+            Conversion::String => {
+                let s = self.next_arg().unwrap_or_default();
+                self.write_str(s.as_bytes(), precision, width, flags);
+            }
+            Conversion::EscapedString => {
+                let s = self.next_arg().unwrap_or_default();
+                let (expanded, keep_going) = escape::expand_backslash_escapes(
+                    s,
+                    escape::EscapeExpansionMode::PrintfBArgument,
+                )?;
+
+                self.write_str(expanded.as_slice(), precision, width, flags);
+
+                // A \c in the argument stops all further output.
+                if !keep_going {
+                    return Ok(false);
+                }
+            }
+            Conversion::Quoted => {
+                let quoted = escape::quote_if_needed(
+                    self.next_arg().unwrap_or_default(),
+                    escape::QuoteMode::BackslashEscape,
+                );
+                self.write_str(quoted.as_bytes(), precision, width, flags);
+            }
+            Conversion::QuotedAfterPrecision => {
+                let s = self.next_arg().unwrap_or_default().as_bytes();
+                let s = truncate_to_precision(s, precision);
+                let quoted = escape::quote_if_needed(
+                    String::from_utf8_lossy(s).as_ref(),
+                    escape::QuoteMode::BackslashEscape,
+                );
+                self.write_str(quoted.as_bytes(), None, width, flags);
+            }
+            Conversion::Time(time_format) => {
+                let seconds = if self.next_arg_index < self.args.len() {
+                    self.next_signed_arg()?
+                } else {
+                    -1
+                };
+
+                let formatted = self.format_time(time_format.as_str(), seconds);
+                self.write_str(formatted.as_bytes(), precision, width, flags);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn format_time(&self, time_format: &str, seconds: i64) -> String {
+        let time = match seconds {
+            // -1 refers to the current time.
+            -1 => chrono::Local::now(),
+            // -2 refers to the time the shell was started.
+            -2 => chrono::DateTime::from(self.shell_start_time),
+            seconds => chrono::DateTime::<chrono::Utc>::from_timestamp(seconds, 0)
+                .map_or_else(chrono::Local::now, |time| {
+                    time.with_timezone(&chrono::Local)
+                }),
+        };
+
+        let time_format = if time_format.is_empty() {
+            "%X"
+        } else {
+            time_format
+        };
+
+        let mut formatted = String::new();
+        if write!(formatted, "{}", time.format(time_format)).is_err() {
+            // The format was invalid; pass it through as-is.
+            time_format.clone_into(&mut formatted);
+        }
+
+        formatted
+    }
+
+    fn next_arg(&mut self) -> Option<&'a str> {
+        let arg = self.args.get(self.next_arg_index)?;
+        self.next_arg_index += 1;
+        Some(arg.as_str())
+    }
+
+    fn next_signed_arg(&mut self) -> Result<i64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0);
+        };
+
+        if let Some(value) = char_constant_value(arg) {
+            return Ok(i64::from(value));
+        }
+
+        let parsed = parse_integer_prefix(arg);
+        self.check_fully_parsed(arg, parsed.unparsed)?;
+
+        let value = parsed.magnitude.and_then(|magnitude| {
+            let magnitude = i128::from(magnitude);
+            i64::try_from(if parsed.negative {
+                -magnitude
+            } else {
+                magnitude
+            })
+            .ok()
+        });
+
+        if let Some(value) = value {
+            Ok(value)
+        } else {
+            self.report_out_of_range(arg)?;
+            Ok(if parsed.negative { i64::MIN } else { i64::MAX })
+        }
+    }
 
-        let output = cmd.output()?;
+    /// Retrieves the next argument as a field width or precision; as in bash, values
+    /// outside the range of a C `int` are reported and clamped.
+    fn next_count_arg(&mut self) -> Result<i64, error::Error> {
+        let arg = self.args.get(self.next_arg_index);
+        let value = self.next_signed_arg()?;
 
-        let stdout = String::from_utf8(output.stdout)?;
-        let stderr = String::from_utf8(output.stderr)?;
+        let clamped = value.clamp(i64::from(i32::MIN), i64::from(i32::MAX));
+        if clamped != value {
+            if let Some(arg) = arg {
+                self.report_out_of_range(arg)?;
+            }
+        }
+
+        Ok(clamped)
+    }
+
+    fn next_unsigned_arg(&mut self) -> Result<u64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0);
+        };
+
+        if let Some(value) = char_constant_value(arg) {
+            return Ok(u64::from(value));
+        }
+
+        let parsed = parse_integer_prefix(arg);
+        self.check_fully_parsed(arg, parsed.unparsed)?;
+
+        // N.B. As with strtoumax(), negative values wrap around.
+        if let Some(magnitude) = parsed.magnitude {
+            Ok(if parsed.negative {
+                magnitude.wrapping_neg()
+            } else {
+                magnitude
+            })
+        } else {
+            self.report_out_of_range(arg)?;
+            Ok(u64::MAX)
+        }
+    }
+
+    fn next_float_arg(&mut self) -> Result<f64, error::Error> {
+        let Some(arg) = self.next_arg() else {
+            return Ok(0.0);
+        };
+
+        if let Some(value) = char_constant_value(arg) {
+            return Ok(f64::from(value));
+        }
+
+        let (value, unparsed) = parse_float_prefix(arg);
+        self.check_fully_parsed(arg, unparsed)?;
+
+        Ok(value)
+    }
+
+    fn check_fully_parsed(&mut self, arg: &str, unparsed: &str) -> Result<(), error::Error> {
+        if unparsed.is_empty() {
+            return Ok(());
+        }
+
+        let mut arg_chars = arg.chars();
+        let message = match (arg_chars.next(), arg_chars.next()) {
+            (Some('0'), Some(c)) if c.is_ascii_digit() => "invalid octal number",
+            (Some('0'), Some('x' | 'X')) => "invalid hex number",
+            _ => "invalid number",
+        };
+
+        self.report_error(std::format!("{arg}: {message}").as_str())
+    }
+
+    fn report_out_of_range(&mut self, arg: &str) -> Result<(), error::Error> {
+        writeln!(
+            self.stderr,
+            "{}: warning: {arg}: Numerical result out of range",
+            self.command_name
+        )?;
+
+        Ok(())
+    }
+
+    fn report_error(&mut self, message: &str) -> Result<(), error::Error> {
+        writeln!(self.stderr, "{}: {message}", self.command_name)?;
+        self.failed = true;
+
+        Ok(())
+    }
+
+    fn write_number(
+        &mut self,
+        prefix: &str,
+        digits: &str,
+        width: usize,
+        flags: FormatFlags,
+        zero_pad_allowed: bool,
+    ) {
+        let zero_pad = flags.zero_pad && zero_pad_allowed && !flags.left_justify;
+        self.write_padded(prefix.as_bytes(), digits.as_bytes(), width, flags, zero_pad);
+    }
+
+    fn write_str(&mut self, s: &[u8], precision: Option<usize>, width: usize, flags: FormatFlags) {
+        let s = truncate_to_precision(s, precision);
+        self.write_padded(&[], s, width, flags, false);
+    }
+
+    fn write_padded(
+        &mut self,
+        prefix: &[u8],
+        body: &[u8],
+        width: usize,
+        flags: FormatFlags,
+        zero_pad: bool,
+    ) {
+        let padding = width.saturating_sub(prefix.len() + body.len());
+
+        if flags.left_justify {
+            self.output.extend_from_slice(prefix);
+            self.output.extend_from_slice(body);
+            self.output.extend(std::iter::repeat(b' ').take(padding));
+        } else if zero_pad {
+            self.output.extend_from_slice(prefix);
+            self.output.extend(std::iter::repeat(b'0').take(padding));
+            self.output.extend_from_slice(body);
+        } else {
+            self.output.extend(std::iter::repeat(b' ').take(padding));
+            self.output.extend_from_slice(prefix);
+            self.output.extend_from_slice(body);
+        }
+    }
+}
+
+fn truncate_to_precision(s: &[u8], precision: Option<usize>) -> &[u8] {
+    match precision {
+        Some(precision) if precision < s.len() => &s[..precision],
+        _ => s,
+    }
+}
+
+fn sign_str(negative: bool, flags: FormatFlags) -> &'static str {
+    if negative {
+        "-"
+    } else if flags.always_sign {
+        "+"
+    } else if flags.space_for_sign {
+        " "
+    } else {
+        ""
+    }
+}
+
+fn apply_integer_precision(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        // An explicit zero precision yields no digits at all for a zero value.
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if precision > digits.len() => {
+            let mut padded = "0".repeat(precision - digits.len());
+            padded.push_str(digits.as_str());
+            padded
+        }
+        _ => digits,
+    }
+}
 
-        write!(context.stderr(), "{stderr}")?;
-        context.stderr().flush()?;
+/// Returns the numeric value of a character constant argument (e.g., `'A` or `"A`), if the
+/// argument is one.
+fn char_constant_value(arg: &str) -> Option<u32> {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some('\'' | '"') => Some(chars.next().map_or(0, u32::from)),
+        _ => None,
+    }
+}
+
+struct ParsedInteger<'a> {
+    negative: bool,
+    /// The magnitude of the parsed value, or `None` if it overflowed.
+    magnitude: Option<u64>,
+    /// The portion of the input that could not be parsed.
+    unparsed: &'a str,
+}
+
+/// Parses the longest integer prefix of the given string, following the conventions of
+/// `strtoimax()` with a base of 0: leading whitespace and an optional sign are accepted, and
+/// a `0x` or `0` prefix selects hexadecimal or octal.
+fn parse_integer_prefix(s: &str) -> ParsedInteger<'_> {
+    let trimmed = s.trim_start();
+
+    let (negative, unsigned) = if let Some(rest) = trimmed.strip_prefix('-') {
+        (true, rest)
+    } else {
+        (false, trimmed.strip_prefix('+').unwrap_or(trimmed))
+    };
+
+    let hex_digits = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+        .filter(|rest| rest.starts_with(|c: char| c.is_ascii_hexdigit()));
+
+    let (radix, digits) = if let Some(hex_digits) = hex_digits {
+        (16, hex_digits)
+    } else if unsigned.starts_with('0') {
+        (8, unsigned)
+    } else {
+        (10, unsigned)
+    };
+
+    let digit_count = digits
+        .find(|c: char| !c.is_digit(radix))
+        .unwrap_or(digits.len());
+
+    if digit_count == 0 {
+        return ParsedInteger {
+            negative: false,
+            magnitude: Some(0),
+            unparsed: s,
+        };
+    }
+
+    ParsedInteger {
+        negative,
+        magnitude: u64::from_str_radix(&digits[..digit_count], radix).ok(),
+        unparsed: &digits[digit_count..],
+    }
+}
+
+/// Parses the longest floating-point prefix of the given string (after any leading
+/// whitespace), returning the value and the unparsed remainder.
+fn parse_float_prefix(s: &str) -> (f64, &str) {
+    let trimmed = s.trim_start();
+    let max_len = trimmed
+        .find(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '.' | '+' | '-'))
+        .unwrap_or(trimmed.len());
+
+    (1..=max_len)
+        .rev()
+        .filter(|len| trimmed.is_char_boundary(*len))
+        .find_map(|len| {
+            trimmed[..len]
+                .parse::<f64>()
+                .ok()
+                .map(|value| (value, &trimmed[len..]))
+        })
+        .unwrap_or((0.0, s))
+}
+
+fn format_float_fixed(value: f64, precision: usize, alternate_form: bool) -> String {
+    let exact_precision = precision.min(MAX_EXACT_FLOAT_DIGITS);
+    let mut formatted = std::format!("{value:.exact_precision$}");
+    formatted.extend(std::iter::repeat('0').take(precision - exact_precision));
+
+    if alternate_form && precision == 0 {
+        formatted.push('.');
+    }
+
+    formatted
+}
+
+fn format_float_exponential(value: f64, precision: usize, alternate_form: bool) -> String {
+    let exact_precision = precision.min(MAX_EXACT_FLOAT_DIGITS);
+    let formatted = std::format!("{value:.exact_precision$e}");
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .unwrap_or((formatted.as_str(), "0"));
+    let exponent: i64 = exponent.parse().unwrap_or_default();
+
+    let point = if alternate_form && precision == 0 {
+        "."
+    } else {
+        ""
+    };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
 
-        if output.status.success() {
-            Ok(stdout)
+    let zeros = "0".repeat(precision - exact_precision);
+
+    std::format!(
+        "{mantissa}{zeros}{point}e{exponent_sign}{:02}",
+        exponent.unsigned_abs()
+    )
+}
+
+fn format_float_general(value: f64, precision: usize, alternate_form: bool) -> String {
+    let precision = precision.max(1);
+
+    // Find the exponent that the value would have in exponential form, taking rounding
+    // into account.
+    let exponent = if value == 0.0 {
+        0
+    } else {
+        let formatted = format_float_exponential(value, precision - 1, false);
+        formatted
+            .split_once('e')
+            .and_then(|(_, exponent)| exponent.parse::<i64>().ok())
+            .unwrap_or_default()
+    };
+
+    let precision_as_exponent = i64::try_from(precision).unwrap_or(i64::MAX);
+    let (mut formatted, exponent_suffix) = if exponent < -4 || exponent >= precision_as_exponent {
+        let formatted = format_float_exponential(value, precision - 1, alternate_form);
+        let (mantissa, exponent) = formatted
+            .split_once('e')
+            .unwrap_or((formatted.as_str(), ""));
+        (mantissa.to_owned(), std::format!("e{exponent}"))
+    } else {
+        let decimals = usize::try_from(precision_as_exponent - 1 - exponent).unwrap_or_default();
+        (
+            format_float_fixed(value, decimals, alternate_form),
+            String::new(),
+        )
+    };
+
+    // Unless the alternate form was requested, trailing zeros are removed.
+    if !alternate_form && formatted.contains('.') {
+        let trimmed_len = formatted.trim_end_matches('0').trim_end_matches('.').len();
+        formatted.truncate(trimmed_len);
+    }
+
+    formatted.push_str(exponent_suffix.as_str());
+    formatted
+}
+
+/// Formats the given non-negative, finite value in hexadecimal exponential form. To match
+/// the output of bash (which uses an x87 `long double`), the leading hex digit is normalized
+/// to hold 4 significant bits.
+fn format_float_hex(value: f64, precision: Option<usize>, alternate_form: bool) -> String {
+    const FRACTION_HEX_DIGITS: usize = 15;
+    const FRACTION_BITS: u32 = 60;
+
+    let bits = value.to_bits();
+    let biased_exponent = i64::try_from((bits >> 52) & 0x7ff).unwrap_or_default();
+    let fraction = bits & ((1 << 52) - 1);
+
+    let (mut leading, mut fraction, mut exponent) = if value == 0.0 {
+        (0, 0, 0)
+    } else {
+        let (mantissa, exponent) = if biased_exponent == 0 {
+            (fraction, -1074)
         } else {
-            Err(crate::error::Error::PrintfFailure(
-                output.status.code().unwrap(),
-            ))
+            (fraction | (1 << 52), biased_exponent - 1075)
+        };
+
+        // Shift the mantissa so its top bit is set.
+        let shift = mantissa.leading_zeros();
+        let mantissa = mantissa << shift;
+        let exponent = exponent - i64::from(shift) + i64::from(FRACTION_BITS);
+
+        (
+            mantissa >> FRACTION_BITS,
+            mantissa & ((1 << FRACTION_BITS) - 1),
+            exponent,
+        )
+    };
+
+    let mut digits = std::format!("{fraction:0width$x}", width = FRACTION_HEX_DIGITS);
+
+    match precision {
+        None => {
+            let trimmed_len = digits.trim_end_matches('0').len();
+            digits.truncate(trimmed_len);
+        }
+        Some(precision) if precision < FRACTION_HEX_DIGITS => {
+            // Round to the requested number of digits, with ties going to even.
+            let dropped_bits =
+                u32::try_from(FRACTION_HEX_DIGITS - precision).unwrap_or_default() * 4;
+            let remainder = fraction & ((1 << dropped_bits) - 1);
+            let half = 1 << (dropped_bits - 1);
+            fraction >>= dropped_bits;
+
+            let last_kept_is_odd = if precision == 0 {
+                leading & 1 == 1
+            } else {
+                fraction & 1 == 1
+            };
+
+            if remainder > half || (remainder == half && last_kept_is_odd) {
+                fraction += 1;
+                if fraction >> (FRACTION_BITS - dropped_bits) != 0 {
+                    fraction = 0;
+                    leading += 1;
+                }
+            }
+
+            if leading == 0x10 {
+                leading = 1;
+                exponent += 4;
+            }
+
+            digits = if precision == 0 {
+                String::new()
+            } else {
+                std::format!("{fraction:0precision$x}")
+            };
+        }
+        Some(precision) => {
+            digits.extend(std::iter::repeat('0').take(precision - digits.len()));
         }
     }
+
+    let point = if !digits.is_empty() || alternate_form {
+        "."
+    } else {
+        ""
+    };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+
+    std::format!(
+        "0x{leading:x}{point}{digits}p{exponent_sign}{}",
+        exponent.unsigned_abs()
+    )
 }
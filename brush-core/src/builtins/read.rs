@@ -47,9 +47,10 @@ pub(crate) struct ReadCommand {
     silent: bool,
 
     /// Specify timeout in seconds; fail if the timeout elapses before
-    /// input is completed.
-    #[clap(short = 't')]
-    timeout_in_seconds: Option<usize>,
+    /// input is completed. A timeout of 0 only checks whether input is
+    /// available.
+    #[clap(short = 't', allow_hyphen_values = true)]
+    timeout_in_seconds: Option<String>,
 
     /// File descriptor to read from instead of stdin.
     #[clap(short = 'u', name = "FD")]
@@ -64,18 +65,30 @@ impl builtins::Command for ReadCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        if self.use_readline {
-            return error::unimp("read -e");
-        }
-        if self.initial_text.is_some() {
-            return error::unimp("read -i");
-        }
         if self.raw_mode {
             tracing::debug!("read -r is not implemented");
         }
-        if self.timeout_in_seconds.is_some() {
-            return error::unimp("read -t");
-        }
+
+        // Figure out the timeout to use; TMOUT provides a default if -t wasn't given.
+        let timeout = if let Some(timeout_str) = &self.timeout_in_seconds {
+            if let Some(timeout) = parse_timeout(timeout_str) {
+                Some(timeout)
+            } else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {timeout_str}: invalid timeout specification",
+                    context.command_name
+                )?;
+                return Ok(crate::builtins::ExitCode::Custom(1));
+            }
+        } else {
+            context
+                .shell
+                .env
+                .get_str("TMOUT")
+                .and_then(|value| parse_timeout(value.as_ref()))
+                .filter(|timeout| !timeout.is_zero())
+        };
 
         // Find the input stream to use.
         #[allow(clippy::cast_lossless)]
@@ -88,7 +101,28 @@ impl builtins::Command for ReadCommand {
             context.stdin()
         };
 
-        let input_line = self.read_line(input_stream, context.stdout())?;
+        // A zero timeout just checks for available input without reading any.
+        if timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return if input_stream.poll_for_input(std::time::Duration::ZERO)? {
+                Ok(crate::builtins::ExitCode::Success)
+            } else {
+                Ok(crate::builtins::ExitCode::Custom(1))
+            };
+        }
+
+        let (reason, input_line) = match context.shell.line_editor {
+            Some(line_editor) if self.can_use_line_editor(timeout) && input_stream.is_term() => {
+                let prompt = self.prompt.as_deref().unwrap_or_default();
+                let initial_text = self.initial_text.as_deref().unwrap_or_default();
+                match line_editor(prompt, initial_text) {
+                    Ok(Some(line)) => (ReadTermination::Delimiter, Some(line)),
+                    Ok(None) => (ReadTermination::EndOfInput, None),
+                    Err(error::Error::Interrupted) => (ReadTermination::CtrlC, None),
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => self.read_line(input_stream, context.stdout(), timeout)?,
+        };
 
         if let Some(input_line) = input_line {
             let mut fields: VecDeque<_> = split_line_by_ifs(&context, input_line.as_str());
@@ -152,7 +186,15 @@ impl builtins::Command for ReadCommand {
                 )?;
             }
 
-            Ok(crate::builtins::ExitCode::Success)
+            if matches!(reason, ReadTermination::Timeout) {
+                // Match bash, which reports a timeout as if interrupted by SIGALRM.
+                Ok(crate::builtins::ExitCode::Custom(128 + 14))
+            } else {
+                Ok(crate::builtins::ExitCode::Success)
+            }
+        } else if matches!(reason, ReadTermination::CtrlC) {
+            // Match bash, which reports an interrupted read as if interrupted by SIGINT.
+            Ok(crate::builtins::ExitCode::Custom(128 + 2))
         } else {
             Ok(crate::builtins::ExitCode::Custom(1))
         }
@@ -164,14 +206,28 @@ enum ReadTermination {
     EndOfInput,
     CtrlC,
     Limit,
+    Timeout,
 }
 
 impl ReadCommand {
+    /// Checks whether input may be read using the shell's line editor, which only reads
+    /// whole lines, with echo and without a timeout. Otherwise, input is read directly so
+    /// that the requested options are honored.
+    fn can_use_line_editor(&self, timeout: Option<std::time::Duration>) -> bool {
+        self.use_readline
+            && timeout.is_none()
+            && self.delimiter.is_none()
+            && self.return_after_n_chars.is_none()
+            && self.return_after_n_chars_no_delimiter.is_none()
+            && !self.silent
+    }
+
     fn read_line(
         &self,
         mut input_file: openfiles::OpenFile,
         mut output_file: openfiles::OpenFile,
-    ) -> Result<Option<String>, error::Error> {
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(ReadTermination, Option<String>), error::Error> {
         let orig_term_attr = self.setup_terminal_settings(&input_file)?;
 
         let delimiter = if self.return_after_n_chars_no_delimiter.is_some() {
@@ -192,9 +248,30 @@ impl ReadCommand {
         }
 
         let mut line = String::new();
+
+        // Without a line editor, any initial text for editing is displayed and taken as
+        // the start of the line, with what's typed appended to it.
+        if self.use_readline && input_file.is_term() {
+            if let Some(initial_text) = &self.initial_text {
+                write!(output_file, "{initial_text}")?;
+                output_file.flush()?;
+                line.push_str(initial_text);
+            }
+        }
+
         let mut buffer = [0; 1]; // 1-byte buffer
 
+        let deadline = timeout.and_then(|timeout| std::time::Instant::now().checked_add(timeout));
+
         let reason = loop {
+            // If there's a deadline, then make sure input shows up before it passes.
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                if !input_file.poll_for_input(remaining)? {
+                    break ReadTermination::Timeout;
+                }
+            }
+
             let n = input_file.read(&mut buffer)?;
             if n == 0 {
                 break ReadTermination::EndOfInput; // EOF reached.
@@ -236,20 +313,25 @@ impl ReadCommand {
             input_file.set_term_attr(orig_term_attr)?;
         }
 
-        match reason {
+        let line = match reason {
             ReadTermination::EndOfInput => {
                 if line.is_empty() {
-                    Ok(None)
+                    None
                 } else {
-                    Ok(Some(line))
+                    Some(line)
                 }
             }
             ReadTermination::CtrlC => {
                 // Discard the input and return.
-                Ok(None)
+                None
             }
-            ReadTermination::Delimiter | ReadTermination::Limit => Ok(Some(line)),
-        }
+            // N.B. On timeout, any partial input read so far is still returned.
+            ReadTermination::Delimiter | ReadTermination::Limit | ReadTermination::Timeout => {
+                Some(line)
+            }
+        };
+
+        Ok((reason, line))
     }
 
     fn setup_terminal_settings(
@@ -281,3 +363,13 @@ fn split_line_by_ifs(context: &commands::ExecutionContext<'_>, line: &str) -> Ve
         .map(|field| field.to_owned())
         .collect()
 }
+
+/// Parses a timeout specification, given as a (possibly fractional) number of seconds.
+fn parse_timeout(s: &str) -> Option<std::time::Duration> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let seconds: f64 = s.parse().ok()?;
+    std::time::Duration::try_from_secs_f64(seconds).ok()
+}
@@ -256,6 +256,8 @@ impl builtins::Command for SetCommand {
             }
         }
 
+        context.shell.apply_options_to_env();
+
         for (i, arg) in self.positional_args.iter().enumerate() {
             if arg == "-" && i == 0 {
                 continue;
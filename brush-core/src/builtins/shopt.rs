@@ -138,6 +138,8 @@ impl builtins::Command for ShoptCommand {
                 }
             }
 
+            context.shell.apply_options_to_env();
+
             Ok(return_value)
         }
     }
@@ -0,0 +1,32 @@
+use clap::Parser;
+use std::io::Write;
+
+use crate::{builtins, commands, timing};
+
+/// Report the CPU time consumed by the shell and its children.
+#[derive(Parser)]
+pub(crate) struct TimesCommand {}
+
+impl builtins::Command for TimesCommand {
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        let times = timing::ProcessTimes::get()?;
+
+        writeln!(
+            context.stdout(),
+            "{} {}",
+            timing::format_duration(&times.self_user, 3, true),
+            timing::format_duration(&times.self_system, 3, true),
+        )?;
+        writeln!(
+            context.stdout(),
+            "{} {}",
+            timing::format_duration(&times.children_user, 3, true),
+            timing::format_duration(&times.children_system, 3, true),
+        )?;
+
+        Ok(builtins::ExitCode::Success)
+    }
+}
@@ -37,7 +37,7 @@ impl builtins::Command for TrapCommand {
             Ok(builtins::ExitCode::Success)
         } else if self.args.len() == 1 {
             let signal = self.args[0].as_str();
-            Self::remove_all_handlers(&mut context, signal.parse()?);
+            Self::remove_all_handlers(&mut context, signal.parse()?)?;
             Ok(builtins::ExitCode::Success)
         } else {
             let handler = &self.args[0];
@@ -47,7 +47,7 @@ impl builtins::Command for TrapCommand {
                 signal_types.push(signal.parse()?);
             }
 
-            Self::register_handler(&mut context, signal_types, handler.as_str());
+            Self::register_handler(&mut context, signal_types, handler.as_str())?;
             Ok(builtins::ExitCode::Success)
         }
     }
@@ -66,7 +66,7 @@ impl TrapCommand {
         context: &commands::ExecutionContext<'_>,
         signal_type: TrapSignal,
     ) -> Result<(), error::Error> {
-        if let Some(handler) = context.shell.traps.handlers.get(&signal_type) {
+        if let Some(handler) = context.shell.get_trap_handler(signal_type) {
             writeln!(context.stdout(), "trap -- '{handler}' {signal_type}")?;
         }
         Ok(())
@@ -75,20 +75,21 @@ impl TrapCommand {
     fn remove_all_handlers(
         context: &mut crate::commands::ExecutionContext<'_>,
         signal: TrapSignal,
-    ) {
-        context.shell.traps.remove_handlers(signal);
+    ) -> Result<(), error::Error> {
+        context.shell.remove_trap_handlers(signal)
     }
 
     fn register_handler(
         context: &mut crate::commands::ExecutionContext<'_>,
         signals: Vec<TrapSignal>,
         handler: &str,
-    ) {
+    ) -> Result<(), error::Error> {
         for signal in signals {
             context
                 .shell
-                .traps
-                .register_handler(signal, handler.to_owned());
+                .register_trap_handler(signal, handler.to_owned())?;
         }
+
+        Ok(())
     }
 }
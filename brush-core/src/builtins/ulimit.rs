@@ -0,0 +1,572 @@
+use clap::Parser;
+use nix::libc::rlim_t;
+use nix::sys::resource::{Resource, RLIM_INFINITY};
+use std::io::Write;
+
+use crate::{builtins, commands, error, Shell};
+
+/// Display or set resource limits for the shell and the processes it starts.
+#[derive(Parser)]
+pub(crate) struct ULimitCommand {
+    /// Use the hard resource limit.
+    #[clap(short = 'H')]
+    hard: bool,
+
+    /// Use the soft resource limit.
+    #[clap(short = 'S')]
+    soft: bool,
+
+    /// Display all current limits.
+    #[clap(short = 'a')]
+    all: bool,
+
+    /// Maximum real-time scheduled CPU time without blocking, in microseconds.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'R', name = "RTTIME")]
+    real_time_non_blocking_time: Option<Option<String>>,
+
+    /// Maximum size of core files created.
+    #[clap(short = 'c', name = "CORE")]
+    core_file_size: Option<Option<String>>,
+
+    /// Maximum size of a process's data segment.
+    #[clap(short = 'd', name = "DATA")]
+    data_seg_size: Option<Option<String>>,
+
+    /// Maximum scheduling priority ("nice").
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'e', name = "NICE")]
+    scheduling_priority: Option<Option<String>>,
+
+    /// Maximum size of files written by the shell and its children.
+    #[clap(short = 'f', name = "FSIZE")]
+    file_size: Option<Option<String>>,
+
+    /// Maximum number of pending signals.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'i', name = "SIGPENDING")]
+    pending_signals: Option<Option<String>>,
+
+    /// Maximum size that may be locked into memory.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'l', name = "MEMLOCK")]
+    max_locked_memory: Option<Option<String>>,
+
+    /// Maximum resident set size.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'm', name = "RSS")]
+    max_memory_size: Option<Option<String>>,
+
+    /// Maximum number of open file descriptors.
+    #[clap(short = 'n', name = "NOFILE")]
+    open_files: Option<Option<String>>,
+
+    /// Pipe size in 512-byte blocks; may not be set.
+    #[clap(short = 'p', name = "PIPESIZE")]
+    pipe_size: Option<Option<String>>,
+
+    /// Maximum number of bytes in POSIX message queues.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'q', name = "MSGQUEUE")]
+    posix_message_queues: Option<Option<String>>,
+
+    /// Maximum real-time scheduling priority.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'r', name = "RTPRIO")]
+    real_time_priority: Option<Option<String>>,
+
+    /// Maximum stack size.
+    #[clap(short = 's', name = "STACK")]
+    stack_size: Option<Option<String>>,
+
+    /// Maximum amount of CPU time, in seconds.
+    #[clap(short = 't', name = "CPU")]
+    cpu_time: Option<Option<String>>,
+
+    /// Maximum number of processes available to a single user.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'u', name = "NPROC")]
+    max_user_processes: Option<Option<String>>,
+
+    /// Maximum amount of virtual memory available to the shell.
+    #[clap(short = 'v', name = "AS")]
+    virtual_memory: Option<Option<String>>,
+
+    /// Maximum number of file locks.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[clap(short = 'x', name = "LOCKS")]
+    file_locks: Option<Option<String>>,
+
+    /// New value for the limit; may also be `unlimited`, `hard`, or `soft`.
+    limit: Option<String>,
+
+    /// Option characters of the selected limits, in the order given on the command line.
+    #[clap(skip)]
+    option_order: Vec<char>,
+}
+
+/// Where the value of a limit comes from.
+#[derive(Clone, Copy)]
+enum LimitSource {
+    /// A resource limit managed via getrlimit/setrlimit.
+    Resource(Resource),
+    /// The size of a pipe buffer; this is fixed and may only be queried.
+    PipeSize,
+}
+
+/// Describes a limit that may be displayed or set.
+struct LimitInfo {
+    /// Option character used to select the limit.
+    option: char,
+    /// Human-readable description of the limit.
+    description: &'static str,
+    /// Units that limit values are displayed and specified in, if noteworthy.
+    units: Option<&'static str>,
+    /// Number of the underlying resource's units in a displayed unit.
+    scale: rlim_t,
+    /// Source of the limit.
+    source: LimitSource,
+}
+
+impl LimitInfo {
+    fn new(
+        option: char,
+        description: &'static str,
+        units: Option<&'static str>,
+        scale: rlim_t,
+        source: LimitSource,
+    ) -> Self {
+        Self {
+            option,
+            description,
+            units,
+            scale,
+            source,
+        }
+    }
+
+    fn get(&self, shell: &Shell) -> Result<(rlim_t, rlim_t), nix::errno::Errno> {
+        match self.source {
+            LimitSource::Resource(resource) => {
+                match shell
+                    .subshell_resource_limits
+                    .as_ref()
+                    .and_then(|limits| limits.get(resource))
+                {
+                    Some(limits) => Ok(limits),
+                    None => nix::sys::resource::getrlimit(resource),
+                }
+            }
+            LimitSource::PipeSize => {
+                let pipe_size = rlim_t::try_from(nix::libc::PIPE_BUF).unwrap_or_default();
+                Ok((pipe_size, pipe_size))
+            }
+        }
+    }
+
+    /// Sets the limit. In a subshell, the new limits are only applied to the commands it
+    /// runs, so they're checked here the way setrlimit(2) would check them.
+    fn set(
+        &self,
+        shell: &mut Shell,
+        current_hard: rlim_t,
+        soft: rlim_t,
+        hard: rlim_t,
+    ) -> Result<(), nix::errno::Errno> {
+        match self.source {
+            LimitSource::Resource(resource) => match &mut shell.subshell_resource_limits {
+                Some(limits) => {
+                    if soft > hard {
+                        Err(nix::errno::Errno::EINVAL)
+                    } else if hard > current_hard && !nix::unistd::geteuid().is_root() {
+                        Err(nix::errno::Errno::EPERM)
+                    } else {
+                        limits.set(resource, soft, hard);
+                        Ok(())
+                    }
+                }
+                None => nix::sys::resource::setrlimit(resource, soft, hard),
+            },
+            LimitSource::PipeSize => Err(nix::errno::Errno::EINVAL),
+        }
+    }
+
+    fn format_value(&self, value: rlim_t) -> String {
+        if value == RLIM_INFINITY {
+            "unlimited".into()
+        } else {
+            (value / self.scale).to_string()
+        }
+    }
+
+    fn format_with_description(&self, value: rlim_t) -> String {
+        let units = match self.units {
+            Some(units) => std::format!("({units}, -{}) ", self.option),
+            None => std::format!("(-{}) ", self.option),
+        };
+
+        std::format!(
+            "{:<20} {units:>20}{}",
+            self.description,
+            self.format_value(value)
+        )
+    }
+}
+
+/// Returns the limits supported on this platform, in display order.
+#[allow(clippy::too_many_lines)]
+fn all_limits() -> Vec<LimitInfo> {
+    let mut limits = vec![];
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'R',
+        "real-time non-blocking time",
+        Some("microseconds"),
+        1,
+        LimitSource::Resource(Resource::RLIMIT_RTTIME),
+    ));
+    limits.push(LimitInfo::new(
+        'c',
+        "core file size",
+        Some("blocks"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_CORE),
+    ));
+    limits.push(LimitInfo::new(
+        'd',
+        "data seg size",
+        Some("kbytes"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_DATA),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'e',
+        "scheduling priority",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_NICE),
+    ));
+    limits.push(LimitInfo::new(
+        'f',
+        "file size",
+        Some("blocks"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_FSIZE),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'i',
+        "pending signals",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_SIGPENDING),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'l',
+        "max locked memory",
+        Some("kbytes"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_MEMLOCK),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'm',
+        "max memory size",
+        Some("kbytes"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_RSS),
+    ));
+    limits.push(LimitInfo::new(
+        'n',
+        "open files",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_NOFILE),
+    ));
+    limits.push(LimitInfo::new(
+        'p',
+        "pipe size",
+        Some("512 bytes"),
+        512,
+        LimitSource::PipeSize,
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'q',
+        "POSIX message queues",
+        Some("bytes"),
+        1,
+        LimitSource::Resource(Resource::RLIMIT_MSGQUEUE),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'r',
+        "real-time priority",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_RTPRIO),
+    ));
+    limits.push(LimitInfo::new(
+        's',
+        "stack size",
+        Some("kbytes"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_STACK),
+    ));
+    limits.push(LimitInfo::new(
+        't',
+        "cpu time",
+        Some("seconds"),
+        1,
+        LimitSource::Resource(Resource::RLIMIT_CPU),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'u',
+        "max user processes",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_NPROC),
+    ));
+    limits.push(LimitInfo::new(
+        'v',
+        "virtual memory",
+        Some("kbytes"),
+        1024,
+        LimitSource::Resource(Resource::RLIMIT_AS),
+    ));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    limits.push(LimitInfo::new(
+        'x',
+        "file locks",
+        None,
+        1,
+        LimitSource::Resource(Resource::RLIMIT_LOCKS),
+    ));
+
+    limits
+}
+
+impl builtins::Command for ULimitCommand {
+    /// Override the default [`builtins::Command::new`] function to split up clusters of
+    /// options and to record the order in which limits were selected, since they're
+    /// displayed and set in that order.
+    fn new<I>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let limits = all_limits();
+        let args = split_option_clusters(args, &limits);
+        let mut this = Self::try_parse_from(args.iter())?;
+        this.option_order = limit_options_in_order(args.iter().skip(1), &limits);
+        Ok(this)
+    }
+
+    async fn execute(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        let limits = all_limits();
+
+        if self.all {
+            for limit in &limits {
+                let value = self.get_value(context.shell, limit)?;
+                writeln!(context.stdout(), "{}", limit.format_with_description(value))?;
+            }
+
+            return Ok(builtins::ExitCode::Success);
+        }
+
+        // Pair up the selected limits with any values specified for them; if no limits
+        // were explicitly selected, then the file size limit is implied.
+        let selected = self.selected_options();
+        let mut requests: Vec<(&LimitInfo, Option<&String>)> = self
+            .option_order
+            .iter()
+            .filter_map(|option| {
+                let (_, value) = selected.iter().find(|(selected, _)| selected == option)?;
+                let limit = limits.iter().find(|limit| limit.option == *option)?;
+                Some((limit, *value))
+            })
+            .collect();
+
+        if requests.is_empty() {
+            if let Some(limit) = limits.iter().find(|limit| limit.option == 'f') {
+                requests.push((limit, None));
+            }
+        }
+
+        // A trailing value applies to the last selected limit that doesn't already have one.
+        if let Some(limit_value) = &self.limit {
+            if let Some((_, value)) = requests.iter_mut().rev().find(|(_, v)| v.is_none()) {
+                *value = Some(limit_value);
+            }
+        }
+
+        let show_descriptions = requests.len() > 1;
+        let mut result = builtins::ExitCode::Success;
+
+        for (limit, value) in requests {
+            if let Some(value) = value {
+                let (current_soft, current_hard) = limit.get(context.shell)?;
+
+                let new_value = match value.as_str() {
+                    "unlimited" => RLIM_INFINITY,
+                    "hard" => current_hard,
+                    "soft" => current_soft,
+                    s => {
+                        let parsed = s.parse::<rlim_t>().ok();
+                        if let Some(new_value) = parsed.and_then(|n| n.checked_mul(limit.scale)) {
+                            new_value
+                        } else {
+                            writeln!(
+                                context.stderr(),
+                                "{}: {s}: invalid number",
+                                context.command_name
+                            )?;
+                            result = builtins::ExitCode::Custom(1);
+                            continue;
+                        }
+                    }
+                };
+
+                // If neither -H nor -S was given, then both limits get updated.
+                let (new_soft, new_hard) = match (self.soft, self.hard) {
+                    (true, false) => (new_value, current_hard),
+                    (false, true) => (current_soft, new_value),
+                    _ => (new_value, new_value),
+                };
+
+                if let Err(e) = limit.set(context.shell, current_hard, new_soft, new_hard) {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {}: cannot modify limit: {}",
+                        context.command_name,
+                        limit.description,
+                        e.desc()
+                    )?;
+                    result = builtins::ExitCode::Custom(1);
+                }
+            } else {
+                let value = self.get_value(context.shell, limit)?;
+                if show_descriptions {
+                    writeln!(context.stdout(), "{}", limit.format_with_description(value))?;
+                } else {
+                    writeln!(context.stdout(), "{}", limit.format_value(value))?;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl ULimitCommand {
+    /// Returns the option characters of the limits selected on the command line,
+    /// along with any value specified for each.
+    fn selected_options(&self) -> Vec<(char, Option<&String>)> {
+        #[allow(unused_mut)]
+        let mut options = vec![
+            ('c', &self.core_file_size),
+            ('d', &self.data_seg_size),
+            ('f', &self.file_size),
+            ('n', &self.open_files),
+            ('p', &self.pipe_size),
+            ('s', &self.stack_size),
+            ('t', &self.cpu_time),
+            ('v', &self.virtual_memory),
+        ];
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        options.extend([
+            ('R', &self.real_time_non_blocking_time),
+            ('e', &self.scheduling_priority),
+            ('i', &self.pending_signals),
+            ('l', &self.max_locked_memory),
+            ('m', &self.max_memory_size),
+            ('q', &self.posix_message_queues),
+            ('r', &self.real_time_priority),
+            ('u', &self.max_user_processes),
+            ('x', &self.file_locks),
+        ]);
+
+        options
+            .into_iter()
+            .filter_map(|(option, value)| value.as_ref().map(|value| (option, value.as_ref())))
+            .collect()
+    }
+
+    /// Retrieves the soft limit, or the hard limit if only -H was given.
+    fn get_value(&self, shell: &Shell, limit: &LimitInfo) -> Result<rlim_t, error::Error> {
+        let (soft, hard) = limit.get(shell)?;
+        if self.hard && !self.soft {
+            Ok(hard)
+        } else {
+            Ok(soft)
+        }
+    }
+}
+
+/// Splits clusters of option characters (e.g., `-cn`) into separate arguments, so that
+/// only the last option in a cluster takes a value that follows it. Arguments holding
+/// anything but option characters are left alone, since the rest of such an argument is
+/// the value of its limit option (e.g., `-c0`).
+fn split_option_clusters(
+    args: impl IntoIterator<Item = String>,
+    limits: &[LimitInfo],
+) -> Vec<String> {
+    let is_option =
+        |c: char| matches!(c, 'H' | 'S' | 'a') || limits.iter().any(|limit| limit.option == c);
+
+    let mut split_args = vec![];
+    let mut args = args.into_iter();
+
+    for arg in args.by_ref() {
+        if arg == "--" {
+            split_args.push(arg);
+            break;
+        }
+
+        match arg.strip_prefix('-') {
+            Some(cluster) if cluster.len() > 1 && cluster.chars().all(is_option) => {
+                split_args.extend(cluster.chars().map(|c| std::format!("-{c}")));
+            }
+            _ => split_args.push(arg),
+        }
+    }
+
+    split_args.extend(args);
+    split_args
+}
+
+/// Returns the option characters of the limits selected by the given arguments, in the
+/// order they appear. Each limit option takes an optional value, so anything following
+/// one in the same argument is its value rather than more options.
+fn limit_options_in_order<'a>(
+    args: impl Iterator<Item = &'a String>,
+    limits: &[LimitInfo],
+) -> Vec<char> {
+    let mut options = vec![];
+
+    for arg in args {
+        if arg == "--" {
+            break;
+        }
+
+        let Some(cluster) = arg.strip_prefix('-') else {
+            continue;
+        };
+
+        if let Some(option) = cluster
+            .chars()
+            .find(|c| limits.iter().any(|limit| limit.option == *c))
+        {
+            options.push(option);
+        }
+    }
+
+    options
+}
@@ -39,17 +39,13 @@ impl builtins::Command for UnsetCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        //
-        // TODO: implement nameref
-        //
-        if self.name_interpretation.name_references {
-            return crate::error::unimp("unset: name references are not yet implemented");
-        }
-
         let unspecified = self.name_interpretation.unspecified();
 
         for name in &self.names {
-            if unspecified || self.name_interpretation.shell_variables {
+            if unspecified
+                || self.name_interpretation.shell_variables
+                || self.name_interpretation.name_references
+            {
                 let parameter =
                     brush_parser::word::parse_parameter(name, &context.shell.parser_options())?;
 
@@ -57,7 +53,26 @@ impl builtins::Command for UnsetCommand {
                     brush_parser::word::Parameter::Positional(_) => continue,
                     brush_parser::word::Parameter::Special(_) => continue,
                     brush_parser::word::Parameter::Named(name) => {
-                        context.shell.env.unset(name.as_str())?.is_some()
+                        // Unless asked to unset the reference itself, we unset the variable
+                        // that a name reference refers to.
+                        let name = if self.name_interpretation.name_references {
+                            name
+                        } else {
+                            context
+                                .shell
+                                .env
+                                .resolve_nameref(name.as_str())?
+                                .into_owned()
+                        };
+
+                        context
+                            .shell
+                            .env
+                            .unset_ignoring_nameref(
+                                name.as_str(),
+                                context.shell.options.localvar_unset,
+                            )?
+                            .is_some()
                     }
                     brush_parser::word::Parameter::NamedWithIndex { name, index } => {
                         // First evaluate the index expression.
@@ -40,7 +40,10 @@ impl builtins::Command for WaitCommand {
             return error::unimp("wait with job specs");
         }
 
-        context.shell.jobs.wait_all().await?;
+        let completed_jobs = context.shell.jobs.wait_all().await?;
+        for job in &completed_jobs {
+            context.shell.on_job_completed(job)?;
+        }
 
         Ok(builtins::ExitCode::Success)
     }
@@ -1,6 +1,6 @@
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
-use std::{borrow::Cow, ffi::OsStr, fmt::Display, process::Stdio, sync::Arc};
+use std::{borrow::Cow, ffi::OsStr, fmt::Display, io::Write, process::Stdio};
 
 use brush_parser::ast;
 #[cfg(unix)]
@@ -8,10 +8,10 @@ use command_fds::{CommandFdExt, FdMapping};
 use itertools::Itertools;
 
 use crate::{
-    builtins, error, escape,
+    builtins, error, escape, functions,
     interp::{self, Execute, ProcessGroupPolicy},
     openfiles::{self, OpenFile, OpenFiles},
-    processes, sys, trace_categories, ExecutionParameters, ExecutionResult, Shell,
+    processes, sys, trace_categories, traps, ExecutionParameters, ExecutionResult, Shell,
 };
 
 /// Represents the result of spawning a command.
@@ -109,6 +109,8 @@ pub struct ExecutionContext<'a> {
     pub command_name: String,
     /// The parameters for the execution.
     pub params: ExecutionParameters,
+    /// The file descriptors opened, closed, or moved by the command's own redirections.
+    pub redirected_fds: Vec<u32>,
 }
 
 impl ExecutionContext<'_> {
@@ -258,6 +260,17 @@ pub(crate) fn compose_std_command<S: AsRef<OsStr>>(
         }
     }
 
+    // Leave out any fds the shell keeps to itself.
+    let close_on_exec_fds: Vec<u32> = open_files
+        .files
+        .keys()
+        .filter(|fd| open_files.is_close_on_exec(**fd))
+        .copied()
+        .collect();
+    for fd in close_on_exec_fds {
+        open_files.files.remove(&fd);
+    }
+
     // Inject any other fds.
     #[cfg(unix)]
     {
@@ -310,8 +323,7 @@ pub(crate) async fn execute(
                 .get(cmd_context.command_name.as_str())
             {
                 // Strip the function name off args.
-                return invoke_shell_function(func_reg.definition.clone(), cmd_context, &args[1..])
-                    .await;
+                return invoke_shell_function(func_reg.clone(), cmd_context, &args[1..]).await;
             }
         }
 
@@ -333,7 +345,10 @@ pub(crate) async fn execute(
                 &args[1..],
             )
         } else {
-            tracing::error!("{}: command not found", cmd_context.command_name);
+            cmd_context.shell.report_error(
+                &error::Error::CommandNotFound(cmd_context.command_name.clone()),
+                &cmd_context.params,
+            )?;
             Ok(CommandSpawnResult::ImmediateExit(127))
         }
     } else {
@@ -406,6 +421,20 @@ pub(crate) fn execute_external_command(
         }
     }
 
+    // Resource limits set in a subshell only apply to the commands it runs.
+    #[cfg(unix)]
+    if let Some(limits) = context
+        .shell
+        .subshell_resource_limits
+        .as_ref()
+        .filter(|limits| !limits.is_empty())
+    {
+        let limits = limits.clone();
+        unsafe {
+            cmd.pre_exec(move || limits.apply());
+        }
+    }
+
     // When tracing is enabled, report.
     tracing::debug!(
         target: trace_categories::COMMANDS,
@@ -438,16 +467,10 @@ pub(crate) fn execute_external_command(
                 sys::terminal::move_self_to_foreground()?;
             }
 
-            if context.shell.options.sh_mode {
-                tracing::error!(
-                    "{}: {}: {}: not found",
-                    context.shell.shell_name.as_ref().unwrap_or(&String::new()),
-                    context.shell.get_current_input_line_number(),
-                    context.command_name
-                );
-            } else {
-                tracing::error!("{}: not found", context.command_name);
-            }
+            context.shell.report_error(
+                &error::Error::CommandFileNotFound(context.command_name.clone()),
+                &context.params,
+            )?;
             Ok(CommandSpawnResult::ImmediateExit(127))
         }
         Err(e) => {
@@ -455,7 +478,7 @@ pub(crate) fn execute_external_command(
                 sys::terminal::move_self_to_foreground()?;
             }
 
-            tracing::error!("error: {}", e);
+            context.shell.report_error(&e.into(), &context.params)?;
             Ok(CommandSpawnResult::ImmediateExit(126))
         }
     }
@@ -472,6 +495,14 @@ async fn execute_builtin_command(
     context: ExecutionContext<'_>,
     args: Vec<CommandArg>,
 ) -> Result<CommandSpawnResult, error::Error> {
+    let shell_is_interactive = context.shell.options.interactive;
+    let gnu_errfmt = context.shell.options.errors_in_gnu_format;
+
+    // The builtin takes ownership of the context, so capture where in the shell's input
+    // we are, and where to report errors, up front in case it fails.
+    let error_context = context.shell.error_context();
+    let mut stderr = context.stderr();
+
     let exit_code = match (builtin.execute_func)(context, args).await {
         Ok(builtin_result) => match builtin_result.exit_code {
             builtins::ExitCode::Success => 0,
@@ -490,7 +521,14 @@ async fn execute_builtin_command(
             }
         },
         Err(e) => {
-            tracing::error!("error: {}", e);
+            writeln!(
+                stderr,
+                "{}",
+                e.format_with_context(&error_context, gnu_errfmt)
+            )?;
+            if !shell_is_interactive && e.is_fatal_to_non_interactive_shell() {
+                return Ok(CommandSpawnResult::ExitShell(1));
+            }
             1
         }
     };
@@ -499,10 +537,11 @@ async fn execute_builtin_command(
 }
 
 pub(crate) async fn invoke_shell_function(
-    function_definition: Arc<ast::FunctionDefinition>,
+    function: functions::FunctionRegistration,
     mut context: ExecutionContext<'_>,
     args: &[CommandArg],
 ) -> Result<CommandSpawnResult, error::Error> {
+    let function_definition = &function.definition;
     let ast::FunctionBody(body, redirects) = &function_definition.body;
 
     // Apply any redirects specified at function definition-time.
@@ -519,14 +558,45 @@ pub(crate) async fn invoke_shell_function(
     // Pass through open files.
     let params = context.params.clone();
 
+    // Unless errtrace (`set -E`) is enabled, the ERR trap isn't inherited by the function;
+    // likewise for the DEBUG and RETURN traps and functrace (`set -T` or `declare -t`).
+    let mut uninherited_traps = vec![];
+    if !context.shell.options.shell_functions_inherit_err_trap {
+        uninherited_traps.push(traps::TrapSignal::Err);
+    }
+    if !context
+        .shell
+        .options
+        .shell_functions_inherit_debug_and_return_traps
+        && !function.traced
+    {
+        uninherited_traps.push(traps::TrapSignal::Debug);
+        uninherited_traps.push(traps::TrapSignal::Return);
+    }
+
     // Note that we're going deeper. Once we do this, we need to make sure we don't bail early
     // before "exiting" the function.
-    context
-        .shell
-        .enter_function(context.command_name.as_str(), &function_definition)?;
+    context.shell.enter_function(
+        context.command_name.as_str(),
+        function_definition,
+        uninherited_traps,
+    )?;
 
     // Invoke the function.
-    let result = body.execute(context.shell, &params).await;
+    let mut result = body.execute(context.shell, &params).await;
+
+    // Run the RETURN trap, if one is in effect in the function; it doesn't run if the
+    // function is exiting the shell.
+    if matches!(&result, Ok(result) if !result.exit_shell) {
+        let trap_result = context
+            .shell
+            .run_registered_trap_handler(traps::TrapSignal::Return, &params)
+            .await;
+
+        if let Some(trap_result) = trap_result.transpose() {
+            result = trap_result;
+        }
+    }
 
     // Clean up parameters so any owned files are closed.
     drop(params);
@@ -537,5 +607,10 @@ pub(crate) async fn invoke_shell_function(
     // Restore positional parameters.
     context.shell.positional_parameters = prior_positional_params;
 
-    Ok(CommandSpawnResult::ImmediateExit(result?.exit_code))
+    let result = result?;
+    if result.exit_shell {
+        Ok(CommandSpawnResult::ExitShell(result.exit_code))
+    } else {
+        Ok(CommandSpawnResult::ImmediateExit(result.exit_code))
+    }
 }
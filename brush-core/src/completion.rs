@@ -550,10 +550,9 @@ impl Spec {
 
         let mut vars_to_remove = vec![];
         for (var, value) in vars_and_values {
-            shell.env.update_or_add(
+            shell.env.update_or_add_shell_managed(
                 var,
                 value,
-                |_| Ok(()),
                 env::EnvironmentLookup::Anywhere,
                 env::EnvironmentScope::Global,
             )?;
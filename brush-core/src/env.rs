@@ -4,6 +4,10 @@ use std::collections::HashMap;
 use crate::error;
 use crate::variables::{self, ShellValue, ShellValueUnsetType, ShellVariable};
 
+/// Maximum number of name references that will be followed when resolving a variable
+/// name; longer chains are treated as circular.
+const MAX_NAMEREF_DEPTH: usize = 8;
+
 /// Represents the policy for looking up variables in a shell environment.
 #[derive(Clone, Copy)]
 pub enum EnvironmentLookup {
@@ -33,6 +37,9 @@ pub enum EnvironmentScope {
 pub struct ShellEnvironment {
     /// Stack of scopes, with the top of the stack being the current scope.
     pub(crate) scopes: Vec<(EnvironmentScope, ShellVariableMap)>,
+    /// Whether or not to auto-export variables on creation or modification; mirrors
+    /// the shell's `allexport` option.
+    pub(crate) export_variables_on_modification: bool,
 }
 
 impl Default for ShellEnvironment {
@@ -46,6 +53,7 @@ impl ShellEnvironment {
     pub fn new() -> Self {
         Self {
             scopes: vec![(EnvironmentScope::Global, ShellVariableMap::new())],
+            export_variables_on_modification: false,
         }
     }
 
@@ -134,12 +142,27 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve an immutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment, following any name references.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to retrieve.
     pub fn get<S: AsRef<str>>(&self, name: S) -> Option<(EnvironmentScope, &ShellVariable)> {
+        let name = self.resolve_nameref(name.as_ref()).ok()?;
+        self.get_ignoring_nameref(name)
+    }
+
+    /// Tries to retrieve an immutable reference to the variable with the given name
+    /// in the environment. If the variable is a name reference, the reference itself
+    /// is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    pub fn get_ignoring_nameref<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> Option<(EnvironmentScope, &ShellVariable)> {
         // Look through scopes, from the top of the stack on down.
         for (scope_type, map) in self.scopes.iter().rev() {
             if let Some(var) = map.get(name.as_ref()) {
@@ -151,7 +174,7 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve a mutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment, following any name references.
     ///
     /// # Arguments
     ///
@@ -160,9 +183,11 @@ impl ShellEnvironment {
         &mut self,
         name: S,
     ) -> Option<(EnvironmentScope, &mut ShellVariable)> {
+        let name = self.resolve_nameref(name.as_ref()).ok()?;
+
         // Look through scopes, from the top of the stack on down.
         for (scope_type, map) in self.scopes.iter_mut().rev() {
-            if let Some(var) = map.get_mut(name.as_ref()) {
+            if let Some(var) = map.get_mut(&name) {
                 return Some((*scope_type, var));
             }
         }
@@ -170,6 +195,52 @@ impl ShellEnvironment {
         None
     }
 
+    /// Resolves the given variable name through any chain of name references, returning
+    /// the name of the variable that is ultimately referenced. Names that aren't name
+    /// references resolve to themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    pub fn resolve_nameref<'a>(&self, name: &'a str) -> Result<Cow<'a, str>, error::Error> {
+        Ok(self
+            .resolve_nameref_using_policy(name, EnvironmentLookup::Anywhere)?
+            .map_or(Cow::Borrowed(name), Cow::Owned))
+    }
+
+    /// Resolves the given variable name through any chain of name references, returning
+    /// the name of the variable that is ultimately referenced, or `None` if the variable
+    /// found using the given lookup policy isn't a name reference.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    /// * `lookup_policy` - The policy to use when looking up the named variable.
+    pub fn resolve_nameref_using_policy(
+        &self,
+        name: &str,
+        lookup_policy: EnvironmentLookup,
+    ) -> Result<Option<String>, error::Error> {
+        let Some(mut target) = self
+            .get_using_policy_ignoring_nameref(name, lookup_policy)
+            .and_then(|var| var.nameref_target())
+        else {
+            return Ok(None);
+        };
+
+        for _ in 0..MAX_NAMEREF_DEPTH {
+            match self
+                .get_ignoring_nameref(target)
+                .and_then(|(_, var)| var.nameref_target())
+            {
+                Some(next) => target = next,
+                None => return Ok(Some(target.to_owned())),
+            }
+        }
+
+        Err(error::Error::CircularNameReference(name.to_owned()))
+    }
+
     /// Tries to retrieve the string value of the variable with the given name in the
     /// environment.
     ///
@@ -199,12 +270,32 @@ impl ShellEnvironment {
     //
 
     /// Tries to unset the variable with the given name in the environment, returning
-    /// whether or not such a variable existed.
+    /// whether or not such a variable existed. Name references are followed, such that
+    /// the referenced variable is the one unset.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to unset.
     pub fn unset(&mut self, name: &str) -> Result<Option<ShellVariable>, error::Error> {
+        let name = self.resolve_nameref(name)?;
+        self.unset_ignoring_nameref(&name, false)
+    }
+
+    /// Tries to unset the variable with the given name in the environment, returning
+    /// whether or not such a variable existed. If the variable is a name reference,
+    /// the reference itself is unset.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to unset.
+    /// * `unset_locals_in_caller_scopes` - Whether a local found in a calling function's
+    ///   scope is left shadowed as unset, as is a local in the current function's scope
+    ///   (per the `localvar_unset` option).
+    pub fn unset_ignoring_nameref(
+        &mut self,
+        name: &str,
+        unset_locals_in_caller_scopes: bool,
+    ) -> Result<Option<ShellVariable>, error::Error> {
         let mut local_count = 0;
         for (scope_type, map) in self.scopes.iter_mut().rev() {
             if matches!(scope_type, EnvironmentScope::Local) {
@@ -214,9 +305,11 @@ impl ShellEnvironment {
             let unset_result = Self::try_unset_in_map(map, name)?;
 
             if unset_result.is_some() {
-                // If we end up finding a local in the top-most local frame, then we replace
-                // it with a placeholder.
-                if matches!(scope_type, EnvironmentScope::Local) && local_count == 1 {
+                // If we end up finding a local in the top-most local frame (or in any local
+                // frame, if so configured), then we replace it with a placeholder.
+                if matches!(scope_type, EnvironmentScope::Local)
+                    && (local_count == 1 || unset_locals_in_caller_scopes)
+                {
                     map.set(
                         name,
                         ShellVariable::new(ShellValue::Unset(ShellValueUnsetType::Untyped)),
@@ -257,7 +350,8 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve an immutable reference to a variable from the environment,
-    /// using the given name and lookup policy.
+    /// using the given name and lookup policy. If the variable found is a name
+    /// reference, the reference is followed.
     ///
     /// # Arguments
     ///
@@ -267,6 +361,26 @@ impl ShellEnvironment {
         &self,
         name: N,
         lookup_policy: EnvironmentLookup,
+    ) -> Option<&ShellVariable> {
+        match self.resolve_nameref_using_policy(name.as_ref(), lookup_policy) {
+            Ok(Some(target)) => self.get_ignoring_nameref(target).map(|(_, var)| var),
+            Ok(None) => self.get_using_policy_ignoring_nameref(name, lookup_policy),
+            Err(_) => None,
+        }
+    }
+
+    /// Tries to retrieve an immutable reference to a variable from the environment,
+    /// using the given name and lookup policy. If the variable is a name reference,
+    /// the reference itself is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    /// * `lookup_policy` - The policy to use when looking up the variable.
+    pub fn get_using_policy_ignoring_nameref<N: AsRef<str>>(
+        &self,
+        name: N,
+        lookup_policy: EnvironmentLookup,
     ) -> Option<&ShellVariable> {
         let mut local_count = 0;
         for (scope_type, var_map) in self.scopes.iter().rev() {
@@ -308,7 +422,8 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve a mutable reference to a variable from the environment,
-    /// using the given name and lookup policy.
+    /// using the given name and lookup policy. If the variable found is a name
+    /// reference, the reference is followed.
     ///
     /// # Arguments
     ///
@@ -318,6 +433,28 @@ impl ShellEnvironment {
         &mut self,
         name: N,
         lookup_policy: EnvironmentLookup,
+    ) -> Option<&mut ShellVariable> {
+        match self.resolve_nameref_using_policy(name.as_ref(), lookup_policy) {
+            Ok(Some(target)) => {
+                self.get_mut_using_policy_ignoring_nameref(target, EnvironmentLookup::Anywhere)
+            }
+            Ok(None) => self.get_mut_using_policy_ignoring_nameref(name, lookup_policy),
+            Err(_) => None,
+        }
+    }
+
+    /// Tries to retrieve a mutable reference to a variable from the environment,
+    /// using the given name and lookup policy. If the variable is a name reference,
+    /// the reference itself is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    /// * `lookup_policy` - The policy to use when looking up the variable.
+    pub fn get_mut_using_policy_ignoring_nameref<N: AsRef<str>>(
+        &mut self,
+        name: N,
+        lookup_policy: EnvironmentLookup,
     ) -> Option<&mut ShellVariable> {
         let mut local_count = 0;
         for (scope_type, var_map) in self.scopes.iter_mut().rev() {
@@ -358,7 +495,8 @@ impl ShellEnvironment {
         None
     }
 
-    /// Update a variable in the environment, or add it if it doesn't already exist.
+    /// Update a variable in the environment, or add it if it doesn't already exist. If
+    /// the `allexport` option is enabled, the variable is also exported.
     ///
     /// # Arguments
     ///
@@ -374,8 +512,55 @@ impl ShellEnvironment {
         updater: impl Fn(&mut ShellVariable) -> Result<(), error::Error>,
         lookup_policy: EnvironmentLookup,
         scope_if_creating: EnvironmentScope,
+    ) -> Result<(), error::Error> {
+        let auto_export = self.export_variables_on_modification;
+        self.update_or_add_impl(
+            name,
+            value,
+            |var| {
+                if auto_export {
+                    var.export();
+                }
+                updater(var)
+            },
+            lookup_policy,
+            scope_if_creating,
+        )
+    }
+
+    /// Update a variable maintained by the shell itself (e.g., `PIPESTATUS`), or add it
+    /// if it doesn't already exist. Unlike with `update_or_add`, the variable is never
+    /// exported on account of the `allexport` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to update or add.
+    /// * `value` - The value to assign to the variable.
+    /// * `lookup_policy` - The policy to use when looking up the variable.
+    /// * `scope_if_creating` - The scope to create the variable in if it doesn't already exist.
+    pub(crate) fn update_or_add_shell_managed<N: Into<String>>(
+        &mut self,
+        name: N,
+        value: variables::ShellValueLiteral,
+        lookup_policy: EnvironmentLookup,
+        scope_if_creating: EnvironmentScope,
+    ) -> Result<(), error::Error> {
+        self.update_or_add_impl(name, value, |_| Ok(()), lookup_policy, scope_if_creating)
+    }
+
+    fn update_or_add_impl<N: Into<String>>(
+        &mut self,
+        name: N,
+        value: variables::ShellValueLiteral,
+        updater: impl Fn(&mut ShellVariable) -> Result<(), error::Error>,
+        lookup_policy: EnvironmentLookup,
+        scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
         let name = name.into();
+        let (name, lookup_policy) = match self.resolve_nameref_using_policy(&name, lookup_policy)? {
+            Some(target) => (target, EnvironmentLookup::Anywhere),
+            None => (name, lookup_policy),
+        };
 
         if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
             var.assign(value, false)?;
@@ -390,6 +575,7 @@ impl ShellEnvironment {
     }
 
     /// Update an array element in the environment, or add it if it doesn't already exist.
+    /// If the `allexport` option is enabled, the variable is also exported.
     ///
     /// # Arguments
     ///
@@ -409,9 +595,18 @@ impl ShellEnvironment {
         scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
         let name = name.into();
+        let (name, lookup_policy) = match self.resolve_nameref_using_policy(&name, lookup_policy)? {
+            Some(target) => (target, EnvironmentLookup::Anywhere),
+            None => (name, lookup_policy),
+        };
+
+        let auto_export = self.export_variables_on_modification;
 
         if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
             var.assign_at_index(index, value, false)?;
+            if auto_export {
+                var.export();
+            }
             updater(var)
         } else {
             let mut var = ShellVariable::new(ShellValue::Unset(ShellValueUnsetType::Untyped));
@@ -422,6 +617,9 @@ impl ShellEnvironment {
                 )])),
                 false,
             )?;
+            if auto_export {
+                var.export();
+            }
             updater(&mut var)?;
 
             self.add(name, var, scope_if_creating)
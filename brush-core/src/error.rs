@@ -44,9 +44,25 @@ pub enum Error {
     FunctionNotFound(String),
 
     /// Command was not found.
-    #[error("command not found: {0}")]
+    #[error("{0}: command not found")]
     CommandNotFound(String),
 
+    /// The file to be executed for a command doesn't exist.
+    #[error("{0}: No such file or directory")]
+    CommandFileNotFound(String),
+
+    /// A syntax error was found near the given token.
+    #[error("syntax error near unexpected token `{0}'")]
+    SyntaxErrorNearToken(String),
+
+    /// The input ended before the command being parsed was complete.
+    #[error("syntax error: unexpected end of file")]
+    SyntaxErrorAtEndOfInput,
+
+    /// The input could not be split into tokens.
+    #[error("{0}")]
+    TokenizingError(String),
+
     /// The requested functionality has not yet been implemented in this shell.
     #[error("UNIMPLEMENTED: {0}")]
     Unimplemented(&'static str),
@@ -103,6 +119,10 @@ pub enum Error {
     #[error("cannot mutate readonly variable")]
     ReadonlyVariable,
 
+    /// A name reference could not be resolved because it refers back to itself.
+    #[error("{0}: circular name reference")]
+    CircularNameReference(String),
+
     /// The indicated pattern is invalid.
     #[error("invalid pattern: '{0}'")]
     InvalidPattern(String),
@@ -119,6 +139,10 @@ pub enum Error {
     #[error("i/o error: {0}")]
     IoError(#[from] std::io::Error),
 
+    /// An unset variable or parameter was referenced while `set -u` was in effect.
+    #[error("{0}: unbound variable")]
+    UnboundVariable(String),
+
     /// Invalid substitution syntax.
     #[error("bad substitution")]
     BadSubstitution,
@@ -174,16 +198,86 @@ pub enum Error {
     OpenFileNotWritable(&'static str),
 
     /// Bad file descriptor.
-    #[error("bad file descriptor: {0}")]
+    #[error("{0}: Bad file descriptor")]
     BadFileDescriptor(u32),
 
-    /// Printf failure
-    #[error("printf failure: {0}")]
-    PrintfFailure(i32),
-
     /// Interrupted
     #[error("interrupted")]
     Interrupted,
+
+    /// An attempt was made to overwrite an existing file while noclobber was enabled.
+    #[error("{0}: cannot overwrite existing file")]
+    CannotOverwriteExistingFile(String),
+
+    /// The target of a redirection didn't expand to a single usable value.
+    #[error("{0}: ambiguous redirect")]
+    AmbiguousRedirect(String),
+
+    /// An invalid character was found in a `TIMEFORMAT` format string.
+    #[error("TIMEFORMAT: `{0}': invalid format character")]
+    InvalidTimeFormatCharacter(char),
+
+    /// An error that occurred at a known point in the shell's input.
+    #[error("{1}")]
+    WithContext(ErrorContext, Box<Error>),
+}
+
+/// Describes where in the shell's input an error occurred.
+#[derive(Clone, Debug)]
+pub struct ErrorContext {
+    /// The name of the script being executed, or of the shell itself when not
+    /// executing a script.
+    pub source: String,
+    /// The name of the function being executed, if any.
+    pub function_name: Option<String>,
+    /// The number of the line being executed, if it's meaningful to report.
+    pub line_number: Option<u32>,
+}
+
+impl ErrorContext {
+    /// Formats the given error message for display, prefixed with this context.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The error message to format.
+    /// * `gnu_format` - Whether to use the GNU `file:line:` format instead of bash's.
+    pub fn format_message(&self, message: &str, gnu_format: bool) -> String {
+        match self.line_number {
+            Some(line_number) if gnu_format => {
+                std::format!("{}:{line_number}: {message}", self.source)
+            }
+            Some(line_number) => std::format!("{}: line {line_number}: {message}", self.source),
+            None => std::format!("{}: {message}", self.source),
+        }
+    }
+}
+
+impl Error {
+    /// Returns whether the error should cause a non-interactive shell to exit.
+    pub(crate) fn is_fatal_to_non_interactive_shell(&self) -> bool {
+        match self {
+            Error::UnboundVariable(_)
+            | Error::EvalError(crate::arithmetic::EvalError::UnboundVariable(_)) => true,
+            Error::WithContext(_, inner) => inner.is_fatal_to_non_interactive_shell(),
+            _ => false,
+        }
+    }
+
+    /// Formats the error for display, prefixed with where in the shell's input it occurred.
+    /// Errors that aren't already annotated with that are attributed to the given context.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context to attribute the error to, if it has none of its own.
+    /// * `gnu_format` - Whether to use the GNU `file:line:` format instead of bash's.
+    pub(crate) fn format_with_context(&self, context: &ErrorContext, gnu_format: bool) -> String {
+        match self {
+            Error::WithContext(own_context, inner) => {
+                own_context.format_message(inner.to_string().as_str(), gnu_format)
+            }
+            _ => context.format_message(self.to_string().as_str(), gnu_format),
+        }
+    }
 }
 
 /// Convenience function for returning an error for unimplemented functionality.
@@ -194,3 +288,51 @@ pub enum Error {
 pub(crate) fn unimp<T>(msg: &'static str) -> Result<T, Error> {
     Err(Error::Unimplemented(msg))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_with_context() {
+        let context = ErrorContext {
+            source: String::from("script.sh"),
+            function_name: None,
+            line_number: Some(12),
+        };
+        let error = Error::CommandNotFound(String::from("foo"));
+
+        assert_eq!(
+            error.format_with_context(&context, false),
+            "script.sh: line 12: foo: command not found"
+        );
+        assert_eq!(
+            error.format_with_context(&context, true),
+            "script.sh:12: foo: command not found"
+        );
+    }
+
+    #[test]
+    fn test_format_with_own_context() {
+        let own_context = ErrorContext {
+            source: String::from("lib.sh"),
+            function_name: Some(String::from("myfunc")),
+            line_number: Some(3),
+        };
+        let other_context = ErrorContext {
+            source: String::from("brush"),
+            function_name: None,
+            line_number: None,
+        };
+        let error = Error::WithContext(own_context, Box::new(Error::BadSubstitution));
+
+        assert_eq!(
+            error.format_with_context(&other_context, false),
+            "lib.sh: line 3: bad substitution"
+        );
+        assert_eq!(
+            Error::BadSubstitution.format_with_context(&other_context, true),
+            "brush: bad substitution"
+        );
+    }
+}
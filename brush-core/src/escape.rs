@@ -8,6 +8,8 @@ use crate::error;
 pub(crate) enum EscapeExpansionMode {
     EchoBuiltin,
     AnsiCQuotes,
+    PrintfFormatString,
+    PrintfBArgument,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -29,10 +31,14 @@ pub(crate) fn expand_backslash_escapes(
             Some('b') => result.push(b'\x08'),
             Some('c') => {
                 match mode {
-                    EscapeExpansionMode::EchoBuiltin => {
+                    EscapeExpansionMode::EchoBuiltin | EscapeExpansionMode::PrintfBArgument => {
                         // Stop all additional output!
                         return Ok((result, false));
                     }
+                    EscapeExpansionMode::PrintfFormatString => {
+                        result.push(b'\\');
+                        result.push(b'c');
+                    }
                     EscapeExpansionMode::AnsiCQuotes => {
                         if let Some(_next_next) = it.next() {
                             return error::unimp("control character in ANSI C quotes");
@@ -50,29 +56,46 @@ pub(crate) fn expand_backslash_escapes(
             Some('t') => result.push(b'\t'),
             Some('v') => result.push(b'\x0b'),
             Some('\\') => result.push(b'\\'),
-            Some('\'') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\''),
-            Some('\"') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\"'),
-            Some('?') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'?'),
-            Some('0') => {
-                // Consume 0-3 valid octal chars
+            Some(c @ ('\'' | '"' | '?'))
+                if matches!(
+                    mode,
+                    EscapeExpansionMode::AnsiCQuotes | EscapeExpansionMode::PrintfFormatString
+                ) =>
+            {
+                result.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            Some(c @ '0'..='7')
+                if c == '0'
+                    || matches!(
+                        mode,
+                        EscapeExpansionMode::PrintfFormatString
+                            | EscapeExpansionMode::PrintfBArgument
+                    ) =>
+            {
+                // A leading '0' may be followed by up to 3 more octal chars, except in printf
+                // format strings, where an octal escape has at most 3 chars in total.
+                let max_additional =
+                    if c == '0' && !matches!(mode, EscapeExpansionMode::PrintfFormatString) {
+                        3
+                    } else {
+                        2
+                    };
+
                 let mut taken_so_far = 0;
-                let mut octal_chars: String = it
-                    .take_while_ref(|c| {
-                        if taken_so_far < 3 && matches!(*c, '0'..='7') {
+                let octal_chars: String = std::iter::once(c)
+                    .chain(it.take_while_ref(|c| {
+                        if taken_so_far < max_additional && matches!(*c, '0'..='7') {
                             taken_so_far += 1;
                             true
                         } else {
                             false
                         }
-                    })
+                    }))
                     .collect();
 
-                if octal_chars.is_empty() {
-                    octal_chars.push('0');
-                }
-
-                let value = u8::from_str_radix(octal_chars.as_str(), 8)?;
-                result.push(value);
+                // N.B. Values that don't fit in a byte are truncated.
+                let value = u32::from_str_radix(octal_chars.as_str(), 8)?;
+                result.push(value.to_le_bytes()[0]);
             }
             Some('x') => {
                 // Consume 1-2 valid hex chars
@@ -181,24 +204,65 @@ pub(crate) fn quote_if_needed(s: &str, mode: QuoteMode) -> Cow<'_, str> {
 }
 
 fn escape_with_backslash(s: &str) -> Cow<'_, str> {
-    if !s.chars().any(needs_escaping) {
+    // An empty string can't be escaped; quote it so it isn't lost.
+    if s.is_empty() {
+        return "''".into();
+    }
+
+    // Control characters can't be escaped with a backslash; fall back to ANSI-C quoting.
+    if s.chars().any(|c| c.is_ascii_control()) {
+        return escape_with_ansi_c_quoting(s).into();
+    }
+
+    let needs_backslash = |(i, c): (usize, char)| {
+        needs_escaping(c) || c == '\'' || (i == 0 && matches!(c, '~' | '#'))
+    };
+
+    if !s.char_indices().any(needs_backslash) {
         return s.into();
     }
 
     let mut output = String::new();
 
-    // TODO: Handle other interesting sequences.
+    for (i, c) in s.char_indices() {
+        if needs_backslash((i, c)) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+
+    output.into()
+}
+
+fn escape_with_ansi_c_quoting(s: &str) -> String {
+    let mut output = String::from("$'");
+
     for c in s.chars() {
         match c {
-            c if needs_escaping(c) => {
+            '\x07' => output.push_str("\\a"),
+            '\x08' => output.push_str("\\b"),
+            '\x1b' => output.push_str("\\E"),
+            '\x0c' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\x0b' => output.push_str("\\v"),
+            '\\' => output.push_str("\\\\"),
+            '\'' => output.push_str("\\'"),
+            c if c.is_ascii_control() => {
+                // Use a 3-digit octal escape.
+                let value = u32::from(c);
                 output.push('\\');
-                output.push(c);
+                for shift in [6, 3, 0] {
+                    output.extend(char::from_digit((value >> shift) & 0o7, 8));
+                }
             }
             c => output.push(c),
         }
     }
 
-    output.into()
+    output.push('\'');
+    output
 }
 
 fn escape_with_quoting(s: &str) -> Cow<'_, str> {
@@ -244,7 +308,19 @@ mod tests {
     fn test_backslash_escape() {
         assert_eq!(quote_if_needed("a", QuoteMode::BackslashEscape), "a");
         assert_eq!(quote_if_needed("a b", QuoteMode::BackslashEscape), r"a\ b");
-        assert_eq!(quote_if_needed("", QuoteMode::BackslashEscape), "");
+        assert_eq!(quote_if_needed("", QuoteMode::BackslashEscape), "''");
+        assert_eq!(
+            quote_if_needed("~a'b", QuoteMode::BackslashEscape),
+            r"\~a\'b"
+        );
+        assert_eq!(
+            quote_if_needed("a\tb", QuoteMode::BackslashEscape),
+            r"$'a\tb'"
+        );
+        assert_eq!(
+            quote_if_needed("\x01", QuoteMode::BackslashEscape),
+            r"$'\001'"
+        );
     }
 
     #[test]
@@ -294,4 +370,27 @@ mod tests {
         assert_echo_expands_to(r"\u2620", "☠");
         assert_echo_expands_to(r"\U0001f602", "😂");
     }
+
+    fn assert_printf_expands_to(unexpanded: &str, mode: EscapeExpansionMode, expected: &str) {
+        assert_eq!(
+            String::from_utf8(expand_backslash_escapes(unexpanded, mode).unwrap().0).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_printf_expansion() {
+        let format = EscapeExpansionMode::PrintfFormatString;
+        assert_printf_expands_to(r"\101", format, "A");
+        assert_printf_expands_to(r"\0101", format, "\x081");
+        assert_printf_expands_to(r"\c", format, "\\c");
+        assert_printf_expands_to(r#"\'\"\?"#, format, r#"'"?"#);
+
+        let b_arg = EscapeExpansionMode::PrintfBArgument;
+        assert_printf_expands_to(r"\101", b_arg, "A");
+        assert_printf_expands_to(r"\0101", b_arg, "A");
+        assert_printf_expands_to(r"\1010", b_arg, "A0");
+        assert_printf_expands_to(r#"\'\"\?"#, b_arg, r#"\'\"\?"#);
+        assert_printf_expands_to(r"a\cb", b_arg, "a");
+    }
 }
@@ -440,11 +440,6 @@ impl<'a> WordExpander<'a> {
     async fn basic_expand(&mut self, word: &str) -> Result<Expansion, error::Error> {
         tracing::debug!(target: trace_categories::EXPANSION, "Basic expanding: '{word}'");
 
-        //
-        // TODO: Brace expansion in unquoted pieces
-        // Issue #42
-        //
-
         //
         // Expand: tildes, parameters, command substitutions, arithmetic.
         //
@@ -459,33 +454,49 @@ impl<'a> WordExpander<'a> {
         Ok(coalesce_expansions(expansions))
     }
 
-    /// Apply tilde-expansion, parameter expansion, command substitution, and arithmetic expansion;
-    /// then perform field splitting and pathname expansion.
+    /// Apply brace expansion, tilde-expansion, parameter expansion, command substitution, and
+    /// arithmetic expansion; then perform field splitting and pathname expansion.
     pub async fn full_expand_with_splitting(
         &mut self,
         word: &str,
     ) -> Result<Vec<String>, error::Error> {
-        // Perform basic expansion first.
-        let basic_expansion = self.basic_expand(word).await?;
+        let mut result = vec![];
 
-        // Then split.
-        let fields: Vec<WordField> = self.split_fields(basic_expansion);
+        // Perform brace expansion first; each resulting word is expanded independently.
+        for brace_expanded_word in self.brace_expand_if_needed(word)? {
+            // Perform basic expansion.
+            let basic_expansion = self.basic_expand(brace_expanded_word.as_str()).await?;
 
-        // Now expand pathnames if necessary. This also unquotes as a side effect.
-        let result = fields
-            .into_iter()
-            .flat_map(|field| {
+            // Then split.
+            let fields: Vec<WordField> = self.split_fields(basic_expansion);
+
+            // Now expand pathnames if necessary. This also unquotes as a side effect.
+            for field in fields {
                 if self.shell.options.disable_filename_globbing {
-                    vec![String::from(field)]
+                    result.push(String::from(field));
                 } else {
-                    self.expand_pathnames_in_field(field)
+                    result.extend(self.expand_pathnames_in_field(field));
                 }
-            })
-            .collect();
+            }
+        }
 
         Ok(result)
     }
 
+    fn brace_expand_if_needed(&self, word: &str) -> Result<Vec<String>, error::Error> {
+        if !self.shell.options.perform_brace_expansion {
+            return Ok(vec![word.to_owned()]);
+        }
+
+        if let Some(pieces) =
+            brush_parser::word::parse_brace_expansions(word, &self.parser_options)?
+        {
+            Ok(brush_parser::word::generate_brace_expansions(pieces))
+        } else {
+            Ok(vec![word.to_owned()])
+        }
+    }
+
     fn split_fields(&self, expansion: Expansion) -> Vec<WordField> {
         let ifs = self.shell.get_ifs();
 
@@ -646,7 +657,13 @@ impl<'a> WordExpander<'a> {
             brush_parser::word::WordPiece::BackquotedCommandSubstitution(s)
             | brush_parser::word::WordPiece::CommandSubstitution(s) => {
                 // Insantiate a subshell to run the command in.
-                let mut subshell = self.shell.clone();
+                let mut subshell = self.shell.new_subshell();
+
+                // Command substitutions don't inherit errexit (`set -e`) unless requested.
+                if !subshell.options.command_subst_inherits_errexit && !subshell.options.posix_mode
+                {
+                    subshell.options.exit_on_nonzero_command_exit = false;
+                }
 
                 // Set up pipe so we can read the output.
                 let (reader, writer) = sys::pipes::pipe()?;
@@ -659,7 +676,9 @@ impl<'a> WordExpander<'a> {
                 params.process_group_policy = ProcessGroupPolicy::SameProcessGroup;
 
                 // Run the command.
-                let result = subshell.run_string(s, &params).await?;
+                let result = subshell.run_nested_string(s, &params).await;
+                subshell.run_exit_trap_handler(&params).await?;
+                let result = result?;
 
                 // Make sure the subshell and params are closed; among other things, this
                 // ensures they're not holding onto the write end of the pipe.
@@ -719,7 +738,9 @@ impl<'a> WordExpander<'a> {
                 test_type,
                 default_value,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self
+                    .expand_parameter_allowing_unset(&parameter, indirect)
+                    .await?;
                 let default_value = default_value.as_ref().map_or_else(|| "", |v| v.as_str());
 
                 match (test_type, expanded_parameter.classify()) {
@@ -739,7 +760,9 @@ impl<'a> WordExpander<'a> {
                 test_type,
                 default_value,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self
+                    .expand_parameter_allowing_unset(&parameter, indirect)
+                    .await?;
                 let default_value = default_value.as_ref().map_or_else(|| "", |v| v.as_str());
 
                 match (test_type, expanded_parameter.classify()) {
@@ -763,7 +786,9 @@ impl<'a> WordExpander<'a> {
                 test_type,
                 error_message,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self
+                    .expand_parameter_allowing_unset(&parameter, indirect)
+                    .await?;
                 let error_message = error_message.as_ref().map_or_else(|| "", |v| v.as_str());
 
                 match (test_type, expanded_parameter.classify()) {
@@ -783,7 +808,9 @@ impl<'a> WordExpander<'a> {
                 test_type,
                 alternative_value,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self
+                    .expand_parameter_allowing_unset(&parameter, indirect)
+                    .await?;
                 let alternative_value = alternative_value
                     .as_ref()
                     .map_or_else(|| "", |v| v.as_str());
@@ -939,7 +966,7 @@ impl<'a> WordExpander<'a> {
                     match var.value() {
                         ShellValue::IndexedArray(_)
                         | ShellValue::AssociativeArray(_)
-                        | ShellValue::Random => {
+                        | ShellValue::Dynamic(_) => {
                             let equals_or_nothing = if assignable_value_str.is_empty() {
                                 ""
                             } else {
@@ -1200,6 +1227,41 @@ impl<'a> WordExpander<'a> {
         parameter: &brush_parser::word::Parameter,
         indirect: bool,
     ) -> Result<Expansion, error::Error> {
+        let expansion = self
+            .expand_parameter_allowing_unset(parameter, indirect)
+            .await?;
+
+        // Unless the caller is prepared to handle it, referencing an unset parameter is an
+        // error when `set -u` is in effect.
+        if expansion.undefined && self.shell.options.treat_unset_variables_as_error {
+            return Err(error::Error::UnboundVariable(unbound_parameter_name(
+                parameter,
+            )));
+        }
+
+        Ok(expansion)
+    }
+
+    async fn expand_parameter_allowing_unset(
+        &mut self,
+        parameter: &brush_parser::word::Parameter,
+        indirect: bool,
+    ) -> Result<Expansion, error::Error> {
+        // Indirection through a name reference yields the name of the referenced variable.
+        if indirect {
+            if let brush_parser::word::Parameter::Named(name) = parameter {
+                if let Some((_, var)) = self.shell.env.get_ignoring_nameref(name) {
+                    if var.is_treated_as_nameref() {
+                        return Ok(var
+                            .nameref_target()
+                            .map_or_else(Expansion::undefined, |target| {
+                                Expansion::from(target.to_owned())
+                            }));
+                    }
+                }
+            }
+        }
+
         let expansion = self.expand_parameter_without_indirect(parameter).await?;
         if !indirect {
             Ok(expansion)
@@ -1217,6 +1279,20 @@ impl<'a> WordExpander<'a> {
         &mut self,
         parameter: &brush_parser::word::Parameter,
     ) -> Result<Expansion, error::Error> {
+        // A name reference that can't be resolved is warned about and treated as unset.
+        if let brush_parser::word::Parameter::Named(name)
+        | brush_parser::word::Parameter::NamedWithIndex { name, .. }
+        | brush_parser::word::Parameter::NamedWithAllIndices { name, .. } = parameter
+        {
+            if let Err(warning @ error::Error::CircularNameReference(_)) =
+                self.shell.env.resolve_nameref(name)
+            {
+                self.shell
+                    .report_warning_on(&warning, &self.shell.open_files)?;
+                return Ok(Expansion::undefined());
+            }
+        }
+
         match parameter {
             brush_parser::word::Parameter::Positional(p) => {
                 if *p == 0 {
@@ -1347,7 +1423,8 @@ impl<'a> WordExpander<'a> {
                         return Ok(Expansion::from(pid.to_string()));
                     }
                 }
-                Ok(Expansion::from(String::new()))
+                // N.B. Until a background job has been started, this parameter is unset.
+                Ok(Expansion::undefined())
             }
             brush_parser::word::SpecialParameter::ShellName => Ok(Expansion::from(
                 self.shell
@@ -1556,7 +1633,39 @@ fn to_initial_capitals(s: &str) -> String {
     result
 }
 
-fn valid_variable_name(s: &str) -> bool {
+/// Returns the name used to refer to a parameter in diagnostics; like bash, positional
+/// and special parameters are reported with their leading '$'.
+fn unbound_parameter_name(parameter: &brush_parser::word::Parameter) -> String {
+    match parameter {
+        brush_parser::word::Parameter::Positional(p) => std::format!("${p}"),
+        brush_parser::word::Parameter::Named(name) => name.to_owned(),
+        brush_parser::word::Parameter::NamedWithIndex { name, index } => {
+            std::format!("{name}[{index}]")
+        }
+        brush_parser::word::Parameter::NamedWithAllIndices { name, concatenate } => {
+            std::format!("{name}[{}]", if *concatenate { "*" } else { "@" })
+        }
+        brush_parser::word::Parameter::Special(special) => {
+            let name = match special {
+                brush_parser::word::SpecialParameter::AllPositionalParameters {
+                    concatenate: true,
+                } => "*",
+                brush_parser::word::SpecialParameter::AllPositionalParameters {
+                    concatenate: false,
+                } => "@",
+                brush_parser::word::SpecialParameter::PositionalParameterCount => "#",
+                brush_parser::word::SpecialParameter::LastExitStatus => "?",
+                brush_parser::word::SpecialParameter::CurrentOptionFlags => "-",
+                brush_parser::word::SpecialParameter::ProcessId => "$",
+                brush_parser::word::SpecialParameter::LastBackgroundProcessId => "!",
+                brush_parser::word::SpecialParameter::ShellName => "0",
+            };
+            std::format!("${name}")
+        }
+    }
+}
+
+pub(crate) fn valid_variable_name(s: &str) -> bool {
     let mut cs = s.chars();
     match cs.next() {
         Some(c) if c.is_ascii_alphabetic() || c == '_' => {
@@ -1626,6 +1735,41 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_brace_expansion() -> Result<()> {
+        let options = crate::shell::CreateOptions::default();
+        let mut shell = crate::shell::Shell::new(&options).await?;
+
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, "a{b,c}d").await?,
+            vec!["abd", "acd"]
+        );
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, "{1..3}").await?,
+            vec!["1", "2", "3"]
+        );
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, "{a,b}{1,2}").await?,
+            vec!["a1", "a2", "b1", "b2"]
+        );
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, r#""{a,b}""#).await?,
+            vec!["{a,b}"]
+        );
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, "{a}").await?,
+            vec!["{a}"]
+        );
+
+        shell.options.perform_brace_expansion = false;
+        assert_eq!(
+            full_expand_and_split_str(&mut shell, "{a,b}").await?,
+            vec!["{a,b}"]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_field_splitting() -> Result<()> {
         let options = crate::shell::CreateOptions::default();
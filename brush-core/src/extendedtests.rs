@@ -50,10 +50,12 @@ async fn apply_unary_predicate(
     let expanded_operand = expansion::basic_expand_word(shell, operand).await?;
 
     if shell.options.print_commands_and_arguments {
-        shell.trace_command(std::format!(
-            "[[ {op} {} ]]",
-            escape::quote_if_needed(&expanded_operand, escape::QuoteMode::Quote)
-        ))?;
+        shell
+            .trace_command(std::format!(
+                "[[ {op} {} ]]",
+                escape::quote_if_needed(&expanded_operand, escape::QuoteMode::Quote)
+            ))
+            .await?;
     }
 
     apply_unary_predicate_to_str(op, expanded_operand.as_str(), shell)
@@ -175,7 +177,12 @@ pub(crate) fn apply_unary_predicate_to_str(
         }
         ast::UnaryPredicate::ShellVariableIsSetAndAssigned => Ok(shell.env.is_set(operand)),
         ast::UnaryPredicate::ShellVariableIsSetAndNameRef => {
-            error::unimp("unary extended test predicate: ShellVariableIsSetAndNameRef")
+            if let Some((_, var)) = shell.env.get_ignoring_nameref(operand) {
+                Ok(var.is_treated_as_nameref()
+                    && !matches!(var.value(), variables::ShellValue::Unset(_)))
+            } else {
+                Ok(false)
+            }
         }
     }
 }
@@ -191,7 +198,9 @@ async fn apply_binary_predicate(
     match op {
         ast::BinaryPredicate::StringMatchesRegex => {
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             let s = expansion::basic_expand_word(shell, left).await?;
@@ -225,7 +234,9 @@ async fn apply_binary_predicate(
             let substring = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {s} {op} {substring} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {substring} ]]"))
+                    .await?;
             }
 
             Ok(s.contains(substring.as_str()))
@@ -244,7 +255,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             // TODO: According to docs, should be lexicographical order of the current locale.
@@ -255,7 +268,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             // TODO: According to docs, should be lexicographical order of the current locale.
@@ -266,7 +281,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -280,7 +297,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -294,7 +313,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -308,7 +329,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -322,7 +345,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -336,7 +361,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -358,7 +385,9 @@ async fn apply_binary_predicate(
 
             if shell.options.print_commands_and_arguments {
                 let expanded_right = expansion::basic_expand_word(shell, right).await?;
-                shell.trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))
+                    .await?;
             }
 
             pattern.exactly_matches(s.as_str())
@@ -372,7 +401,9 @@ async fn apply_binary_predicate(
 
             if shell.options.print_commands_and_arguments {
                 let expanded_right = expansion::basic_expand_word(shell, right).await?;
-                shell.trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))
+                    .await?;
             }
 
             let eq = pattern.exactly_matches(s.as_str())?;
@@ -16,6 +16,15 @@ impl FunctionEnv {
         self.functions.get(name)
     }
 
+    /// Tries to retrieve a mutable reference to the registration for a function by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the function to retrieve.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut FunctionRegistration> {
+        self.functions.get_mut(name)
+    }
+
     /// Unregisters a function from the environment.
     ///
     /// # Arguments
@@ -32,8 +41,13 @@ impl FunctionEnv {
     /// * `name` - The name of the function to update.
     /// * `definition` - The new definition for the function.
     pub fn update(&mut self, name: String, definition: Arc<brush_parser::ast::FunctionDefinition>) {
-        self.functions
-            .insert(name, FunctionRegistration { definition });
+        self.functions.insert(
+            name,
+            FunctionRegistration {
+                definition,
+                traced: false,
+            },
+        );
     }
 
     /// Returns an iterator over the functions registered in this environment.
@@ -47,4 +61,7 @@ impl FunctionEnv {
 pub struct FunctionRegistration {
     /// The definition of the function.
     pub definition: Arc<brush_parser::ast::FunctionDefinition>,
+    /// Whether the function has the trace attribute (`declare -t`), causing it to inherit
+    /// the `DEBUG` and `RETURN` traps.
+    pub traced: bool,
 }
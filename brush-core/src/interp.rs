@@ -1,7 +1,8 @@
 use brush_parser::ast::{self, CommandPrefixOrSuffixItem};
 use itertools::Itertools;
 use std::collections::VecDeque;
-use std::io::Write;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
 #[cfg(target_os = "linux")]
 use std::os::fd::{AsFd, AsRawFd};
 #[cfg(unix)]
@@ -17,7 +18,7 @@ use crate::shell::Shell;
 use crate::variables::{
     ArrayLiteral, ShellValue, ShellValueLiteral, ShellValueUnsetType, ShellVariable,
 };
-use crate::{error, expansion, extendedtests, jobs, openfiles, processes, sys, traps};
+use crate::{error, expansion, extendedtests, jobs, openfiles, processes, sys, timing, traps};
 
 /// Encapsulates the result of executing a command.
 #[derive(Debug, Default)]
@@ -192,8 +193,13 @@ impl Execute for ast::CompoundList {
                 result = ao_list.execute(shell, params).await?;
             }
 
-            // Check for early return.
-            if result.return_from_function_or_script {
+            // Run handlers for any trapped signals received in the meantime.
+            if let Some(trap_result) = shell.run_pending_signal_trap_handlers(params).await? {
+                result = trap_result;
+            }
+
+            // Check for early return or exit.
+            if result.return_from_function_or_script || result.exit_shell {
                 break;
             }
 
@@ -214,7 +220,7 @@ fn spawn_ao_list_in_task<'a>(
     params: &ExecutionParameters,
 ) -> &'a jobs::Job {
     // Clone the inputs.
-    let mut cloned_shell = shell.clone();
+    let mut cloned_shell = shell.new_subshell();
     let cloned_params = params.clone();
     let cloned_ao_list = ao_list.clone();
 
@@ -222,9 +228,13 @@ fn spawn_ao_list_in_task<'a>(
     cloned_shell.options.interactive = false;
 
     let join_handle = tokio::spawn(async move {
-        cloned_ao_list
+        let result = cloned_ao_list
             .execute(&mut cloned_shell, &cloned_params)
-            .await
+            .await;
+
+        cloned_shell.run_exit_trap_handler(&cloned_params).await?;
+
+        result
     });
 
     let job = shell.jobs.add_as_current(jobs::Job::new(
@@ -243,9 +253,14 @@ impl Execute for ast::AndOrList {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let mut result = self.first.execute(shell, params).await?;
+        // N.B. Only the last pipeline in the list is subject to errexit (`set -e`).
+        let mut result = if self.additional.is_empty() {
+            self.first.execute(shell, params).await?
+        } else {
+            execute_ignoring_errexit(&self.first, shell, params).await?
+        };
 
-        for next_ao in &self.additional {
+        for (i, next_ao) in self.additional.iter().enumerate() {
             // Check for exit/return
             if result.exit_shell || result.return_from_function_or_script {
                 break;
@@ -274,13 +289,31 @@ impl Execute for ast::AndOrList {
                 continue;
             }
 
-            result = pipeline.execute(shell, params).await?;
+            result = if i + 1 == self.additional.len() {
+                pipeline.execute(shell, params).await?
+            } else {
+                execute_ignoring_errexit(pipeline, shell, params).await?
+            };
         }
 
         Ok(result)
     }
 }
 
+/// Executes the given command in a context in which the errexit (`set -e`) option is
+/// ignored, along with everything the command invokes (e.g., shell functions).
+async fn execute_ignoring_errexit<T: Execute + Sync>(
+    command: &T,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    shell.errexit_ignore_depth += 1;
+    let result = command.execute(shell, params).await;
+    shell.errexit_ignore_depth -= 1;
+
+    result
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::Pipeline {
     async fn execute(
@@ -288,12 +321,37 @@ impl Execute for ast::Pipeline {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        // Spawn all the processes required for the pipeline, connecting outputs/inputs with pipes
-        // as needed.
-        let spawn_results = spawn_pipeline_processes(self, shell, params).await?;
+        shell.set_current_line_number(u32::try_from(self.location.line).unwrap_or_default());
+
+        // A pipeline whose status is inverted is exempt from errexit (`set -e`), as is
+        // everything it runs.
+        if self.bang {
+            shell.errexit_ignore_depth += 1;
+        }
+
+        let stopwatch = if self.timed.is_some() {
+            Some(timing::Stopwatch::start()?)
+        } else {
+            None
+        };
+
+        // An empty pipeline (i.e., a bare `time`) runs nothing, and trivially succeeds.
+        let result = if self.seq.is_empty() {
+            update_pipestatus_var(shell, &[0]).map(|()| ExecutionResult::success())
+        } else {
+            spawn_and_wait_for_pipeline(self, shell, params).await
+        };
+
+        if self.bang {
+            shell.errexit_ignore_depth -= 1;
+        }
 
-        // Wait for the processes.
-        let mut result = wait_for_pipeline_processes(self, spawn_results, shell).await?;
+        if let (Some(timed), Some(stopwatch)) = (&self.timed, stopwatch) {
+            report_pipeline_timings(timed, &stopwatch, shell, params)?;
+        }
+
+        // Make sure any error is attributed to where in the input it occurred.
+        let mut result = result.map_err(|e| shell.add_error_context(e))?;
 
         // Invert the exit code if requested.
         if self.bang {
@@ -302,10 +360,121 @@ impl Execute for ast::Pipeline {
 
         shell.last_exit_status = result.exit_code;
 
+        // If this is a failure of the sort that errexit (`set -e`) applies to, then run the
+        // ERR trap and exit the shell if errexit is enabled.
+        if errexit_applies(self, shell, &result) {
+            if let Some(trap_result) = shell
+                .run_registered_trap_handler(traps::TrapSignal::Err, params)
+                .await?
+            {
+                result = trap_result;
+            } else if shell.options.exit_on_nonzero_command_exit {
+                result.exit_shell = true;
+            }
+        }
+
         Ok(result)
     }
 }
 
+fn report_pipeline_timings(
+    timed: &ast::PipelineTimed,
+    stopwatch: &timing::Stopwatch,
+    shell: &Shell,
+    params: &ExecutionParameters,
+) -> Result<(), error::Error> {
+    let timings = stopwatch.elapsed()?;
+
+    let format = match timed {
+        ast::PipelineTimed::Timed => shell.env.get("TIMEFORMAT").map_or_else(
+            || timing::DEFAULT_TIME_FORMAT.to_owned(),
+            |(_, v)| v.value().to_cow_string().to_string(),
+        ),
+        ast::PipelineTimed::TimedWithPosixOutput => timing::POSIX_TIME_FORMAT.to_owned(),
+    };
+
+    // A null format suppresses the report altogether.
+    if format.is_empty() {
+        return Ok(());
+    }
+
+    match timings.format(&format) {
+        Ok(output) => {
+            if let Some(stderr) = params.open_files.stderr() {
+                writeln!(stderr.try_dup()?, "{output}")?;
+            }
+        }
+        Err(e) => shell.report_error(&e, params)?,
+    }
+
+    Ok(())
+}
+
+async fn spawn_and_wait_for_pipeline(
+    pipeline: &ast::Pipeline,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    let prior_pipestatus_update_count = shell.pipestatus_update_count;
+
+    // Spawn all the processes required for the pipeline, connecting outputs/inputs with pipes
+    // as needed.
+    let spawn_results = spawn_pipeline_processes(pipeline, shell, params).await?;
+
+    // Wait for the processes.
+    wait_for_pipeline_processes(
+        pipeline,
+        spawn_results,
+        shell,
+        prior_pipestatus_update_count,
+    )
+    .await
+}
+
+/// Returns whether the failure (if any) reflected in the given result of executing the given
+/// pipeline is one to which errexit (`set -e`) applies. The ERR trap is run under the same
+/// conditions.
+fn errexit_applies(pipeline: &ast::Pipeline, shell: &Shell, result: &ExecutionResult) -> bool {
+    if result.is_success()
+        || pipeline.bang
+        || shell.errexit_ignore_depth > 0
+        || result.exit_shell
+        || result.return_from_function_or_script
+        || result.break_loop.is_some()
+        || result.continue_loop.is_some()
+    {
+        return false;
+    }
+
+    // N.B. Compound commands other than subshells and arithmetic commands aren't checked
+    // themselves; any failing command they contain will have already been checked.
+    match pipeline.seq.as_slice() {
+        [ast::Command::Compound(compound, _)] => matches!(
+            compound,
+            ast::CompoundCommand::Subshell(_) | ast::CompoundCommand::Arithmetic(_)
+        ),
+        _ => true,
+    }
+}
+
+fn update_pipestatus_var(shell: &mut Shell, exit_codes: &[u8]) -> Result<(), error::Error> {
+    let values = exit_codes
+        .iter()
+        .map(|code| (None, code.to_string()))
+        .collect::<Vec<_>>();
+
+    shell.env.update_or_add_shell_managed(
+        "PIPESTATUS",
+        ShellValueLiteral::Array(ArrayLiteral(values)),
+        EnvironmentLookup::Anywhere,
+        EnvironmentScope::Global,
+    )?;
+
+    shell.pipestatus_update_count += 1;
+
+    Ok(())
+}
+
 async fn spawn_pipeline_processes(
     pipeline: &ast::Pipeline,
     shell: &mut Shell,
@@ -316,12 +485,18 @@ async fn spawn_pipeline_processes(
     let mut spawn_results = VecDeque::new();
     let mut process_group_id: Option<i32> = None;
 
+    // Make sure that files persistently opened or closed in the shell since these parameters
+    // were established (e.g., by `exec` or a coprocess) are reflected in the pipeline.
+    let mut params = params.clone();
+    params.open_files.try_update_from(&shell.open_files)?;
+
     for (current_pipeline_index, command) in pipeline.seq.iter().enumerate() {
         // If there's only one command in the pipeline, then we run directly in the current
         // shell. Otherwise, we spawn a separate subshell for each command in the
         // pipeline.
         if pipeline_len > 1 {
-            let mut subshell = shell.clone();
+            let mut subshell = shell.new_subshell();
+
             let mut pipeline_context = PipelineExecutionContext {
                 shell: &mut subshell,
                 current_pipeline_index,
@@ -360,9 +535,11 @@ async fn wait_for_pipeline_processes(
     pipeline: &ast::Pipeline,
     mut process_spawn_results: VecDeque<CommandSpawnResult>,
     shell: &mut Shell,
+    prior_pipestatus_update_count: usize,
 ) -> Result<ExecutionResult, error::Error> {
     let mut result = ExecutionResult::success();
     let mut stopped_children = vec![];
+    let mut exit_codes = vec![];
 
     while let Some(child) = process_spawn_results.pop_front() {
         match child.wait(!stopped_children.is_empty()).await? {
@@ -377,6 +554,28 @@ async fn wait_for_pipeline_processes(
                 stopped_children.push(jobs::JobTask::External(child));
             }
         }
+
+        exit_codes.push(result.exit_code);
+    }
+
+    // With pipefail, the pipeline's status is that of the last (rightmost) command that failed.
+    if shell.options.return_first_failure_from_pipeline {
+        if let Some(exit_code) = exit_codes.iter().rev().find(|code| **code != 0) {
+            result.exit_code = *exit_code;
+            shell.last_exit_status = result.exit_code;
+        }
+    }
+
+    // A lone command that ran pipelines of its own in this shell (e.g., a brace group or a
+    // function) leaves PIPESTATUS reflecting the last of them.
+    if pipeline.seq.len() > 1 || shell.pipestatus_update_count == prior_pipestatus_update_count {
+        update_pipestatus_var(shell, &exit_codes)?;
+    }
+
+    // Commands in a multi-command pipeline each run in their own subshell, so they can't
+    // cause this shell to exit.
+    if pipeline.seq.len() > 1 {
+        result.exit_shell = false;
     }
 
     if shell.options.interactive {
@@ -411,6 +610,13 @@ impl ExecuteInPipeline for ast::Command {
             return Ok(CommandSpawnResult::ImmediateExit(0));
         }
 
+        // N.B. A command needn't start on the same line as its pipeline.
+        if let Some(location) = self.location() {
+            pipeline_context
+                .shell
+                .set_current_line_number(u32::try_from(location.line).unwrap_or_default());
+        }
+
         match self {
             ast::Command::Simple(simple) => simple.execute_in_pipeline(pipeline_context).await,
             ast::Command::Compound(compound, redirects) => {
@@ -474,15 +680,31 @@ impl Execute for ast::CompoundCommand {
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
         match self {
-            ast::CompoundCommand::BraceGroup(ast::BraceGroupCommand(g)) => {
+            ast::CompoundCommand::BraceGroup(ast::BraceGroupCommand(g, _)) => {
                 g.execute(shell, params).await
             }
-            ast::CompoundCommand::Subshell(ast::SubshellCommand(s)) => {
+            ast::CompoundCommand::Subshell(ast::SubshellCommand(s, _)) => {
                 // Clone off a new subshell, and run the body of the subshell there.
-                let mut subshell = shell.clone();
-                s.execute(&mut subshell, params).await
+                let mut subshell = shell.new_subshell();
+
+                // N.B. As if it were a separate process, the subshell reports its own errors
+                // rather than handing them back to this shell.
+                let mut result = match s.execute(&mut subshell, params).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        subshell.report_error(&e, params)?;
+                        ExecutionResult::new(1)
+                    }
+                };
+                subshell.run_exit_trap_handler(params).await?;
+
+                // Exiting the subshell doesn't exit this shell.
+                result.exit_shell = false;
+
+                Ok(result)
             }
             ast::CompoundCommand::ForClause(f) => f.execute(shell, params).await,
+            ast::CompoundCommand::SelectClause(s) => s.execute(shell, params).await,
             ast::CompoundCommand::CaseClause(c) => c.execute(shell, params).await,
             ast::CompoundCommand::IfClause(i) => i.execute(shell, params).await,
             ast::CompoundCommand::WhileClause(w) => {
@@ -493,6 +715,7 @@ impl Execute for ast::CompoundCommand {
             }
             ast::CompoundCommand::Arithmetic(a) => a.execute(shell, params).await,
             ast::CompoundCommand::ArithmeticForClause(a) => a.execute(shell, params).await,
+            ast::CompoundCommand::Coprocess(c) => c.execute(shell, params).await,
         }
     }
 }
@@ -516,24 +739,38 @@ impl Execute for ast::ForClauseCommand {
 
             for value in expanded_values {
                 if shell.options.print_commands_and_arguments {
-                    shell.trace_command(std::format!(
-                        "for {} in {}",
-                        self.variable_name,
-                        unexpanded_values.iter().join(" ")
-                    ))?;
+                    shell
+                        .trace_command(std::format!(
+                            "for {} in {}",
+                            self.variable_name,
+                            unexpanded_values.iter().join(" ")
+                        ))
+                        .await?;
                 }
 
-                // Update the variable.
-                shell.env.update_or_add(
-                    &self.variable_name,
-                    ShellValueLiteral::Scalar(value),
-                    |_| Ok(()),
-                    EnvironmentLookup::Anywhere,
-                    EnvironmentScope::Global,
-                )?;
+                // Update the variable; if it's a name reference, then it's the reference
+                // itself that gets re-pointed at each value in turn.
+                if let Some(nameref) = shell
+                    .env
+                    .get_mut_using_policy_ignoring_nameref(
+                        &self.variable_name,
+                        EnvironmentLookup::Anywhere,
+                    )
+                    .filter(|var| var.is_treated_as_nameref())
+                {
+                    nameref.assign(ShellValueLiteral::Scalar(value), false)?;
+                } else {
+                    shell.env.update_or_add(
+                        &self.variable_name,
+                        ShellValueLiteral::Scalar(value),
+                        |_| Ok(()),
+                        EnvironmentLookup::Anywhere,
+                        EnvironmentScope::Global,
+                    )?;
+                }
 
                 result = self.body.0.execute(shell, params).await?;
-                if result.return_from_function_or_script {
+                if result.return_from_function_or_script || result.exit_shell {
                     break;
                 }
 
@@ -560,6 +797,250 @@ impl Execute for ast::ForClauseCommand {
     }
 }
 
+#[async_trait::async_trait]
+impl Execute for ast::SelectClauseCommand {
+    async fn execute(
+        &self,
+        shell: &mut Shell,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut result = ExecutionResult::success();
+
+        // Expand all values, with splitting enabled; with no values given, we offer
+        // the positional parameters.
+        let values = if let Some(unexpanded_values) = &self.values {
+            let mut expanded_values = vec![];
+            for value in unexpanded_values {
+                let mut expanded = expansion::full_expand_and_split_word(shell, value).await?;
+                expanded_values.append(&mut expanded);
+            }
+            expanded_values
+        } else {
+            shell.positional_parameters.clone()
+        };
+
+        if values.is_empty() {
+            shell.last_exit_status = result.exit_code;
+            return Ok(result);
+        }
+
+        // The menu is displayed up front, and then again only when an empty reply is read.
+        let mut show_menu = true;
+
+        loop {
+            if show_menu {
+                write_select_menu(shell, params, values.as_slice())?;
+            }
+
+            let prompt = shell.env.get("PS3").map_or_else(
+                || String::from("#? "),
+                |(_, v)| v.value().to_cow_string().to_string(),
+            );
+
+            let Some(reply) = read_select_reply(params, prompt.as_str())? else {
+                // Reaching the end of input ends the loop, as a failure.
+                if let Some(mut stdout) = params
+                    .open_files
+                    .stdout()
+                    .map(OpenFile::try_dup)
+                    .transpose()?
+                {
+                    writeln!(stdout)?;
+                }
+
+                result = ExecutionResult::new(1);
+                break;
+            };
+
+            shell.env.update_or_add(
+                "REPLY",
+                ShellValueLiteral::Scalar(reply.clone()),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
+
+            if reply.is_empty() {
+                show_menu = true;
+                continue;
+            }
+            show_menu = false;
+
+            // A reply that doesn't name one of the values selects nothing.
+            let selection = reply
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| values.get(i))
+                .cloned()
+                .unwrap_or_default();
+
+            // Update the variable.
+            shell.env.update_or_add(
+                &self.variable_name,
+                ShellValueLiteral::Scalar(selection),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
+
+            result = self.body.0.execute(shell, params).await?;
+            if result.return_from_function_or_script || result.exit_shell {
+                break;
+            }
+
+            if let Some(continue_count) = &result.continue_loop {
+                if *continue_count > 0 {
+                    return error::unimp("continue with count > 0");
+                }
+
+                result.continue_loop = None;
+            }
+            if let Some(break_count) = &result.break_loop {
+                if *break_count == 0 {
+                    result.break_loop = None;
+                } else {
+                    result.break_loop = Some(*break_count - 1);
+                }
+                break;
+            }
+        }
+
+        shell.last_exit_status = result.exit_code;
+        Ok(result)
+    }
+}
+
+/// Writes the menu for a select clause to standard error, laying out the values in
+/// as many columns as fit within `COLUMNS`, in the same manner as bash.
+fn write_select_menu(
+    shell: &Shell,
+    params: &ExecutionParameters,
+    values: &[String],
+) -> Result<(), error::Error> {
+    const TAB_SIZE: usize = 8;
+
+    let Some(mut stderr) = params
+        .open_files
+        .stderr()
+        .map(OpenFile::try_dup)
+        .transpose()?
+    else {
+        return Ok(());
+    };
+
+    let terminal_width = shell
+        .env
+        .get("COLUMNS")
+        .and_then(|(_, v)| v.value().to_cow_string().parse::<usize>().ok())
+        .filter(|width| *width > 0)
+        .unwrap_or(80);
+
+    let index_len = |index: usize| index.to_string().len();
+
+    // Each entry consists of its index, a ") " separator, and its value; we leave room
+    // for at least 2 spaces between columns.
+    let all_indices_len = index_len(values.len());
+    let max_entry_len =
+        values.iter().map(|v| v.chars().count()).max().unwrap_or(0) + all_indices_len + 4;
+
+    let columns = (terminal_width / max_entry_len).max(1);
+    let mut rows = values.len().div_ceil(columns);
+    if rows == 1 {
+        rows = values.len();
+    }
+
+    let first_column_indices_len = index_len(rows);
+
+    let mut menu = String::new();
+    for row in 0..rows {
+        let mut index = row;
+        let mut pos = 0;
+
+        loop {
+            let indices_len = if pos == 0 {
+                first_column_indices_len
+            } else {
+                all_indices_len
+            };
+
+            let value = &values[index];
+            write!(menu, "{:>indices_len$}) {value}", index + 1)?;
+
+            index += rows;
+            if index >= values.len() {
+                break;
+            }
+
+            // Pad out to the start of the next column, using tabs where possible.
+            let mut from = pos + value.chars().count() + indices_len + 2;
+            let to = pos + max_entry_len;
+            while from < to {
+                if to / TAB_SIZE > from / TAB_SIZE {
+                    menu.push('\t');
+                    from += TAB_SIZE - from % TAB_SIZE;
+                } else {
+                    menu.push(' ');
+                    from += 1;
+                }
+            }
+
+            pos += max_entry_len;
+        }
+
+        menu.push('\n');
+    }
+
+    write!(stderr, "{menu}")?;
+    stderr.flush()?;
+
+    Ok(())
+}
+
+/// Displays the given prompt on standard error and reads a line of input from standard
+/// input for a select clause; returns `None` if the end of input was reached first.
+fn read_select_reply(
+    params: &ExecutionParameters,
+    prompt: &str,
+) -> Result<Option<String>, error::Error> {
+    if let Some(mut stderr) = params
+        .open_files
+        .stderr()
+        .map(OpenFile::try_dup)
+        .transpose()?
+    {
+        write!(stderr, "{prompt}")?;
+        stderr.flush()?;
+    }
+
+    let Some(mut stdin) = params
+        .open_files
+        .stdin()
+        .map(OpenFile::try_dup)
+        .transpose()?
+    else {
+        return Ok(None);
+    };
+
+    // N.B. We read a byte at a time to avoid consuming any input past the end of the line.
+    let mut line = vec![];
+    let mut buffer = [0; 1];
+    loop {
+        if stdin.read(&mut buffer)? == 0 {
+            return Ok(None);
+        }
+
+        if buffer[0] == b'\n' {
+            break;
+        }
+
+        line.push(buffer[0]);
+    }
+
+    Ok(Some(String::from_utf8_lossy(line.as_slice()).to_string()))
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::CaseClauseCommand {
     async fn execute(
@@ -570,7 +1051,9 @@ impl Execute for ast::CaseClauseCommand {
         // N.B. One would think it makes sense to trace the expanded value being switched
         // on, but that's not it.
         if shell.options.print_commands_and_arguments {
-            shell.trace_command(std::format!("case {} in", &self.value))?;
+            shell
+                .trace_command(std::format!("case {} in", &self.value))
+                .await?;
         }
 
         let expanded_value = expansion::basic_expand_word(shell, &self.value).await?;
@@ -627,7 +1110,10 @@ impl Execute for ast::IfClauseCommand {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let condition = self.condition.execute(shell, params).await?;
+        let condition = execute_ignoring_errexit(&self.condition, shell, params).await?;
+        if condition.exit_shell {
+            return Ok(condition);
+        }
 
         if condition.is_success() {
             return self.then.execute(shell, params).await;
@@ -637,7 +1123,12 @@ impl Execute for ast::IfClauseCommand {
             for else_clause in elses {
                 match &else_clause.condition {
                     Some(else_condition) => {
-                        let else_condition_result = else_condition.execute(shell, params).await?;
+                        let else_condition_result =
+                            execute_ignoring_errexit(else_condition, shell, params).await?;
+                        if else_condition_result.exit_shell {
+                            return Ok(else_condition_result);
+                        }
+
                         if else_condition_result.is_success() {
                             return else_clause.body.execute(shell, params).await;
                         }
@@ -673,7 +1164,11 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
         let mut result = ExecutionResult::success();
 
         loop {
-            let condition_result = test_condition.execute(shell, params).await?;
+            let condition_result = execute_ignoring_errexit(test_condition, shell, params).await?;
+
+            if condition_result.exit_shell {
+                return Ok(condition_result);
+            }
 
             if condition_result.is_success() != is_while {
                 break;
@@ -684,7 +1179,7 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
             }
 
             result = body.0.execute(shell, params).await?;
-            if result.return_from_function_or_script {
+            if result.return_from_function_or_script || result.exit_shell {
                 break;
             }
 
@@ -709,6 +1204,115 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
     }
 }
 
+#[async_trait::async_trait]
+impl Execute for ast::CoprocessCommand {
+    async fn execute(
+        &self,
+        shell: &mut Shell,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let name = self.name.as_deref().unwrap_or("COPROC");
+
+        // Set up a pipe in each direction between the shell and the coprocess.
+        let (input_reader, input_writer) = sys::pipes::pipe()?;
+        let (output_reader, output_writer) = sys::pipes::pipe()?;
+
+        // The coprocess runs in a subshell; mark it as not interactive so it doesn't
+        // mess with the terminal.
+        let mut subshell = shell.new_subshell();
+        subshell.options.interactive = false;
+
+        // Hand the coprocess's ends of the pipes to the subshell as its standard input and
+        // output. They're set persistently, so that they take precedence over any files
+        // persistently opened as those descriptors before the subshell was created.
+        subshell
+            .open_files
+            .set_persistent(0, Some(OpenFile::PipeReader(input_reader)));
+        subshell
+            .open_files
+            .set_persistent(1, Some(OpenFile::PipeWriter(output_writer)));
+
+        // As with other in-process subshells, the coprocess is identified by its subshell ID.
+        let pid = subshell.env.get_str("BASHPID").map(|pid| pid.into_owned());
+
+        let body = ast::Pipeline {
+            timed: None,
+            bang: false,
+            seq: vec![self.body.as_ref().clone()],
+            // The coprocess is attributed to the line it was started on.
+            location: brush_parser::SourcePosition {
+                line: i32::try_from(shell.current_line_number).unwrap_or_default(),
+                ..brush_parser::SourcePosition::default()
+            },
+        };
+
+        let mut coproc_params = params.clone();
+        coproc_params
+            .open_files
+            .try_update_from(&subshell.open_files)?;
+
+        let join_handle = tokio::spawn(async move {
+            let result = body.execute(&mut subshell, &coproc_params).await;
+            subshell.run_exit_trap_handler(&coproc_params).await?;
+            result
+        });
+
+        let mut job = jobs::Job::new(
+            [jobs::JobTask::Internal(join_handle)],
+            self.to_string(),
+            jobs::JobState::Running,
+        );
+
+        // Hand the shell's ends of the pipes to the shell. As in bash, they're kept from
+        // the external commands the shell goes on to spawn; otherwise, a command could
+        // hold the coprocess's input open and keep it from ever seeing end-of-file.
+        let read_fd = find_available_fd(&shell.open_files)?;
+        shell
+            .open_files
+            .set_persistent(read_fd, Some(OpenFile::PipeReader(output_reader)));
+        shell.open_files.set_close_on_exec(read_fd, true);
+        let write_fd = find_available_fd(&shell.open_files)?;
+        shell
+            .open_files
+            .set_persistent(write_fd, Some(OpenFile::PipeWriter(input_writer)));
+        shell.open_files.set_close_on_exec(write_fd, true);
+
+        shell.env.update_or_add(
+            name,
+            ShellValueLiteral::Array(ArrayLiteral(vec![
+                (None, read_fd.to_string()),
+                (None, write_fd.to_string()),
+            ])),
+            |_| Ok(()),
+            EnvironmentLookup::Anywhere,
+            EnvironmentScope::Global,
+        )?;
+
+        job.set_coproc_name(name);
+        let job = shell.jobs.add_as_current(job);
+
+        if shell.options.interactive {
+            let job_formatted = job.to_pid_style_string();
+            writeln!(shell.stderr(), "{job_formatted}")?;
+        }
+
+        let pid_var_name = std::format!("{name}_PID");
+        if let Some(pid) = pid {
+            shell.env.update_or_add(
+                pid_var_name.as_str(),
+                ShellValueLiteral::Scalar(pid),
+                |_| Ok(()),
+                EnvironmentLookup::Anywhere,
+                EnvironmentScope::Global,
+            )?;
+        } else {
+            shell.env.unset(pid_var_name.as_str())?;
+        }
+
+        Ok(ExecutionResult::success())
+    }
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::ArithmeticCommand {
     async fn execute(
@@ -749,7 +1353,7 @@ impl Execute for ast::ArithmeticForClauseCommand {
             }
 
             result = self.body.0.execute(shell, params).await?;
-            if result.return_from_function_or_script {
+            if result.return_from_function_or_script || result.exit_shell {
                 break;
             }
 
@@ -819,6 +1423,7 @@ impl ExecuteInPipeline for ast::SimpleCommand {
         let mut assignments = vec![];
         let mut args: Vec<CommandArg> = vec![];
         let mut invoking_declaration_builtin = false;
+        let mut redirected_fds = vec![];
 
         // Set up pipelining.
         setup_pipeline_redirection(&mut params.open_files, context)?;
@@ -831,10 +1436,20 @@ impl ExecuteInPipeline for ast::SimpleCommand {
         {
             match item {
                 CommandPrefixOrSuffixItem::IoRedirect(redirect) => {
-                    if setup_redirect(&mut params.open_files, context.shell, redirect)
-                        .await?
-                        .is_none()
+                    let prior_fds: Vec<u32> = params.open_files.files.keys().copied().collect();
+
+                    if let Some(fd_num) =
+                        setup_redirect(&mut params.open_files, context.shell, redirect).await?
                     {
+                        redirected_fds.push(fd_num);
+
+                        // N.B. Moving a descriptor (e.g., `3>&4-`) also closes its source.
+                        redirected_fds.extend(
+                            prior_fds
+                                .into_iter()
+                                .filter(|fd_num| !params.open_files.files.contains_key(fd_num)),
+                        );
+                    } else {
                         // Something went wrong.
                         return Ok(CommandSpawnResult::ImmediateExit(1));
                     }
@@ -851,6 +1466,7 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                         .open_files
                         .files
                         .insert(installed_fd_num, substitution_file);
+                    params.open_files.set_close_on_exec(installed_fd_num, false);
 
                     args.push(CommandArg::String(std::format!(
                         "/dev/fd/{installed_fd_num}"
@@ -946,17 +1562,26 @@ impl ExecuteInPipeline for ast::SimpleCommand {
             if context.shell.options.print_commands_and_arguments {
                 context
                     .shell
-                    .trace_command(args.iter().map(|arg| arg.quote_for_tracing()).join(" "))?;
+                    .trace_command(args.iter().map(|arg| arg.quote_for_tracing()).join(" "))
+                    .await?;
             }
 
             // TODO: This is adding more complexity here; should be factored out into an appropriate
             // helper.
             if context.shell.traps.handler_depth == 0 {
+                // Keep BASH_COMMAND up to date; it's not updated while trap handlers run, so
+                // they can see the command that triggered them.
+                let full_cmd = args.iter().map(|arg| arg.to_string()).join(" ");
+                context.shell.env.update_or_add_shell_managed(
+                    "BASH_COMMAND",
+                    ShellValueLiteral::Scalar(full_cmd),
+                    EnvironmentLookup::Anywhere,
+                    EnvironmentScope::Global,
+                )?;
+
                 let debug_trap_handler = context
                     .shell
-                    .traps
-                    .handlers
-                    .get(&traps::TrapSignal::Debug)
+                    .get_trap_handler(traps::TrapSignal::Debug)
                     .cloned();
                 if let Some(debug_trap_handler) = debug_trap_handler {
                     // TODO: Confirm whether trap handlers should be executed in the same process
@@ -966,26 +1591,11 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                         process_group_policy: ProcessGroupPolicy::SameProcessGroup,
                     };
 
-                    let full_cmd = args.iter().map(|arg| arg.to_string()).join(" ");
-
-                    // TODO: This shouldn't *just* be set in a trap situation.
-                    context.shell.env.update_or_add(
-                        "BASH_COMMAND",
-                        ShellValueLiteral::Scalar(full_cmd),
-                        |_| Ok(()),
-                        EnvironmentLookup::Anywhere,
-                        EnvironmentScope::Global,
-                    )?;
-
-                    context.shell.traps.handler_depth += 1;
-
                     // TODO: Discard result?
                     let _ = context
                         .shell
-                        .run_string(debug_trap_handler, &handler_params)
+                        .run_trap_handler(debug_trap_handler.as_str(), &handler_params)
                         .await?;
-
-                    context.shell.traps.handler_depth -= 1;
                 }
             }
 
@@ -993,6 +1603,7 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                 shell: context.shell,
                 command_name: cmd_name,
                 params,
+                redirected_fds,
             };
 
             // Execute.
@@ -1073,9 +1684,7 @@ async fn expand_assignment_value(
     let expanded = match value {
         ast::AssignmentValue::Scalar(s) => {
             let expanded_word = expansion::basic_expand_word(shell, s).await?;
-            ast::AssignmentValue::Scalar(ast::Word {
-                value: expanded_word,
-            })
+            ast::AssignmentValue::Scalar(ast::Word::from(expanded_word))
         }
         ast::AssignmentValue::Array(arr) => {
             let mut expanded_values = vec![];
@@ -1156,9 +1765,14 @@ async fn apply_assignment(
 
     if shell.options.print_commands_and_arguments {
         let op = if assignment.append { "+=" } else { "=" };
-        shell.trace_command(std::format!("{}{op}{new_value}", assignment.name))?;
+        shell
+            .trace_command(std::format!("{}{op}{new_value}", assignment.name))
+            .await?;
     }
 
+    // If the name refers to a name reference, then assign to the referenced variable.
+    let variable_name = shell.env.resolve_nameref(variable_name)?.into_owned();
+
     // See if we need to eval an array index.
     if let Some(idx) = &array_index {
         let will_be_indexed_array = if let Some((_, existing_value)) =
@@ -1182,6 +1796,9 @@ async fn apply_assignment(
         }
     }
 
+    // With allexport enabled, any variable we create or modify gets exported.
+    let export = export || shell.options.export_variables_on_modification;
+
     // See if we can find an existing value associated with the variable.
     if let Some((existing_value_scope, existing_value)) = shell.env.get_mut(variable_name.as_str())
     {
@@ -1275,31 +1892,14 @@ pub(crate) async fn setup_redirect(
                 return Err(error::Error::InvalidRedirection);
             }
 
-            let expanded_file_path: PathBuf =
-                shell.get_absolute_path(Path::new(expanded_fields.remove(0).as_str()));
-
-            let opened_file = std::fs::File::options()
-                .create(true)
-                .write(true)
-                .truncate(!*append)
-                .append(*append)
-                .open(expanded_file_path.as_path())
-                .map_err(|err| {
-                    error::Error::RedirectionFailure(
-                        expanded_file_path.to_string_lossy().to_string(),
-                        err,
-                    )
-                })?;
-
-            let stdout_file = OpenFile::File(opened_file);
-            let stderr_file = stdout_file.try_dup()?;
-
-            open_files.files.insert(1, stdout_file);
-            open_files.files.insert(2, stderr_file);
-
-            Ok(Some(1))
+            setup_output_and_error_redirect(
+                open_files,
+                shell,
+                expanded_fields.remove(0).as_str(),
+                *append,
+            )
         }
-        ast::IoRedirect::File(specified_fd_num, kind, target) => {
+        ast::IoRedirect::File(specified_fd, kind, target) => {
             let fd_num;
             let target_file;
             match target {
@@ -1312,7 +1912,6 @@ pub(crate) async fn setup_redirect(
                             options.read(true);
                         }
                         ast::IoFileRedirectKind::Write => {
-                            // TODO: honor noclobber options
                             options.create(true);
                             options.write(true);
                             options.truncate(true);
@@ -1340,7 +1939,12 @@ pub(crate) async fn setup_redirect(
                         }
                     }
 
-                    fd_num = specified_fd_num.unwrap_or(default_fd_if_unspecified);
+                    fd_num = resolve_redirect_fd(
+                        shell,
+                        open_files,
+                        specified_fd.as_ref(),
+                        default_fd_if_unspecified,
+                    );
 
                     let mut expanded_fields =
                         expansion::full_expand_and_split_word(shell, f).await?;
@@ -1349,8 +1953,19 @@ pub(crate) async fn setup_redirect(
                         return Err(error::Error::InvalidRedirection);
                     }
 
+                    let expanded_file = expanded_fields.remove(0);
                     let expanded_file_path: PathBuf =
-                        shell.get_absolute_path(Path::new(expanded_fields.remove(0).as_str()));
+                        shell.get_absolute_path(Path::new(expanded_file.as_str()));
+
+                    // N.B. Clobber (`>|`) redirections ignore noclobber.
+                    if matches!(kind, ast::IoFileRedirectKind::Write) {
+                        apply_noclobber(
+                            shell,
+                            expanded_file.as_str(),
+                            expanded_file_path.as_path(),
+                            &mut options,
+                        )?;
+                    }
 
                     let opened_file =
                         options.open(expanded_file_path.as_path()).map_err(|err| {
@@ -1362,22 +1977,29 @@ pub(crate) async fn setup_redirect(
                     target_file = OpenFile::File(opened_file);
                 }
                 ast::IoFileRedirectTarget::Fd(fd) => {
-                    let default_fd_if_unspecified = match kind {
-                        ast::IoFileRedirectKind::DuplicateInput => 0,
-                        ast::IoFileRedirectKind::DuplicateOutput => 1,
-                        _ => {
-                            return error::unimp("unexpected redirect kind");
-                        }
-                    };
-
-                    fd_num = specified_fd_num.unwrap_or(default_fd_if_unspecified);
+                    return setup_duplicating_redirect(
+                        open_files,
+                        shell,
+                        specified_fd.as_ref(),
+                        kind,
+                        fd.to_string().as_str(),
+                    );
+                }
+                ast::IoFileRedirectTarget::Duplicate(word) => {
+                    let mut expanded_fields =
+                        expansion::full_expand_and_split_word(shell, word).await?;
 
-                    if let Some(f) = open_files.files.get(fd) {
-                        target_file = f.try_dup()?;
-                    } else {
-                        tracing::error!("{}: Bad file descriptor", fd);
-                        return Ok(None);
+                    if expanded_fields.len() != 1 {
+                        return Err(error::Error::AmbiguousRedirect(word.flatten()));
                     }
+
+                    return setup_duplicating_redirect(
+                        open_files,
+                        shell,
+                        specified_fd.as_ref(),
+                        kind,
+                        expanded_fields.remove(0).as_str(),
+                    );
                 }
                 ast::IoFileRedirectTarget::ProcessSubstitution(substitution_kind, subshell_cmd) => {
                     match kind {
@@ -1395,21 +2017,32 @@ pub(crate) async fn setup_redirect(
 
                             target_file = substitution_file.try_dup()?;
                             open_files.files.insert(substitution_fd, substitution_file);
+                            open_files.set_close_on_exec(substitution_fd, false);
 
-                            fd_num = specified_fd_num
-                                .unwrap_or_else(|| get_default_fd_for_redirect_kind(kind));
+                            fd_num = resolve_redirect_fd(
+                                shell,
+                                open_files,
+                                specified_fd.as_ref(),
+                                get_default_fd_for_redirect_kind(kind),
+                            );
                         }
                         _ => return error::unimp("invalid process substitution"),
                     }
                 }
             }
 
-            open_files.files.insert(fd_num, target_file);
+            install_redirected_file(
+                open_files,
+                shell,
+                specified_fd.as_ref(),
+                fd_num,
+                target_file,
+            )?;
             Ok(Some(fd_num))
         }
-        ast::IoRedirect::HereDocument(fd_num, io_here) => {
+        ast::IoRedirect::HereDocument(specified_fd, io_here) => {
             // If not specified, default to stdin (fd 0).
-            let fd_num = fd_num.unwrap_or(0);
+            let fd_num = resolve_redirect_fd(shell, open_files, specified_fd.as_ref(), 0);
 
             // Expand if required.
             let io_here_doc = if io_here.requires_expansion {
@@ -1420,24 +2053,217 @@ pub(crate) async fn setup_redirect(
 
             let f = setup_open_file_with_contents(io_here_doc.as_str())?;
 
-            open_files.files.insert(fd_num, f);
+            install_redirected_file(open_files, shell, specified_fd.as_ref(), fd_num, f)?;
             Ok(Some(fd_num))
         }
-        ast::IoRedirect::HereString(fd_num, word) => {
+        ast::IoRedirect::HereString(specified_fd, word) => {
             // If not specified, default to stdin (fd 0).
-            let fd_num = fd_num.unwrap_or(0);
+            let fd_num = resolve_redirect_fd(shell, open_files, specified_fd.as_ref(), 0);
 
             let mut expanded_word = expansion::basic_expand_word(shell, word).await?;
             expanded_word.push('\n');
 
             let f = setup_open_file_with_contents(expanded_word.as_str())?;
 
-            open_files.files.insert(fd_num, f);
+            install_redirected_file(open_files, shell, specified_fd.as_ref(), fd_num, f)?;
             Ok(Some(fd_num))
         }
     }
 }
 
+fn setup_output_and_error_redirect(
+    open_files: &mut OpenFiles,
+    shell: &Shell,
+    path: &str,
+    append: bool,
+) -> Result<Option<u32>, error::Error> {
+    let expanded_file_path: PathBuf = shell.get_absolute_path(Path::new(path));
+
+    let mut options = std::fs::File::options();
+    options
+        .create(true)
+        .write(true)
+        .truncate(!append)
+        .append(append);
+
+    if !append {
+        apply_noclobber(shell, path, expanded_file_path.as_path(), &mut options)?;
+    }
+
+    let opened_file = options.open(expanded_file_path.as_path()).map_err(|err| {
+        error::Error::RedirectionFailure(expanded_file_path.to_string_lossy().to_string(), err)
+    })?;
+
+    let stdout_file = OpenFile::File(opened_file);
+    let stderr_file = stdout_file.try_dup()?;
+
+    open_files.files.insert(1, stdout_file);
+    open_files.files.insert(2, stderr_file);
+
+    Ok(Some(1))
+}
+
+/// With noclobber (`set -C`) enabled, refuses to overwrite an existing regular file.
+/// If no file exists yet, then the file must be newly created, so that one created in
+/// the meantime isn't overwritten either.
+fn apply_noclobber(
+    shell: &Shell,
+    file: &str,
+    file_path: &Path,
+    options: &mut std::fs::OpenOptions,
+) -> Result<(), error::Error> {
+    if !shell
+        .options
+        .disallow_overwriting_regular_files_via_output_redirection
+    {
+        return Ok(());
+    }
+
+    match std::fs::metadata(file_path) {
+        Ok(metadata) if metadata.is_file() => {
+            Err(error::Error::CannotOverwriteExistingFile(file.to_owned()))
+        }
+        Ok(_) => Ok(()),
+        Err(_) => {
+            options.create_new(true);
+            Ok(())
+        }
+    }
+}
+
+/// Sets up a duplicating redirection (`<&` or `>&`) given its expanded target, which
+/// may name a file descriptor to duplicate, a file descriptor to move (e.g., `3-`),
+/// `-` to close the redirected file descriptor, or--for output--a file.
+fn setup_duplicating_redirect(
+    open_files: &mut OpenFiles,
+    shell: &mut Shell,
+    specified_fd: Option<&ast::IoFd>,
+    kind: &ast::IoFileRedirectKind,
+    target: &str,
+) -> Result<Option<u32>, error::Error> {
+    let is_variable_fd = matches!(specified_fd, Some(ast::IoFd::Variable(_)));
+
+    if target == "-" {
+        let fd_num = match specified_fd {
+            Some(ast::IoFd::Variable(name)) => get_fd_from_var(shell, name)?,
+            Some(ast::IoFd::Number(fd_num)) => *fd_num,
+            None => get_default_fd_for_redirect_kind(kind),
+        };
+
+        open_files.files.remove(&fd_num);
+        if is_variable_fd {
+            shell.open_files.set_persistent(fd_num, None);
+        }
+
+        return Ok(Some(fd_num));
+    }
+
+    let (source_fd, move_fd) = match target.strip_suffix('-') {
+        Some(source_fd) => (source_fd, true),
+        None => (target, false),
+    };
+
+    if !source_fd.is_empty() && source_fd.chars().all(|c| c.is_ascii_digit()) {
+        let source_fd: u32 = source_fd.parse()?;
+
+        let target_file = if let Some(f) = open_files.files.get(&source_fd) {
+            f.try_dup()?
+        } else {
+            shell.report_error_on(&error::Error::BadFileDescriptor(source_fd), open_files)?;
+            return Ok(None);
+        };
+
+        let fd_num = resolve_redirect_fd(
+            shell,
+            open_files,
+            specified_fd,
+            get_default_fd_for_redirect_kind(kind),
+        );
+
+        if move_fd && fd_num != source_fd {
+            open_files.files.remove(&source_fd);
+            if is_variable_fd {
+                shell.open_files.set_persistent(source_fd, None);
+            }
+        }
+
+        install_redirected_file(open_files, shell, specified_fd, fd_num, target_file)?;
+        return Ok(Some(fd_num));
+    }
+
+    // With no file descriptor specified, `>&file` redirects both standard output and
+    // standard error to the file.
+    if matches!(kind, ast::IoFileRedirectKind::DuplicateOutput) && specified_fd.is_none() {
+        return setup_output_and_error_redirect(open_files, shell, target, false);
+    }
+
+    Err(error::Error::AmbiguousRedirect(target.to_owned()))
+}
+
+/// Determines the file descriptor to redirect. If a variable is named instead of a
+/// descriptor, then the lowest unused descriptor of 10 or above is allocated; its
+/// number is only assigned to the variable once the redirection is installed.
+fn resolve_redirect_fd(
+    shell: &Shell,
+    open_files: &OpenFiles,
+    specified_fd: Option<&ast::IoFd>,
+    default_fd: u32,
+) -> u32 {
+    match specified_fd {
+        None => default_fd,
+        Some(ast::IoFd::Number(fd_num)) => *fd_num,
+        Some(ast::IoFd::Variable(_)) => {
+            let mut fd_num = 10;
+            while open_files.files.contains_key(&fd_num)
+                || shell.open_files.files.contains_key(&fd_num)
+            {
+                fd_num += 1;
+            }
+
+            fd_num
+        }
+    }
+}
+
+/// Installs the target of a redirection as the given file descriptor. Descriptors
+/// allocated for a variable-named redirection outlive the command they were opened
+/// for, so they're also persistently opened in the shell, and their number is then
+/// assigned to the variable.
+fn install_redirected_file(
+    open_files: &mut OpenFiles,
+    shell: &mut Shell,
+    specified_fd: Option<&ast::IoFd>,
+    fd_num: u32,
+    file: OpenFile,
+) -> Result<(), error::Error> {
+    if let Some(ast::IoFd::Variable(name)) = specified_fd {
+        shell
+            .open_files
+            .set_persistent(fd_num, Some(file.try_dup()?));
+
+        shell.env.update_or_add(
+            name.as_str(),
+            ShellValueLiteral::Scalar(fd_num.to_string()),
+            |_| Ok(()),
+            EnvironmentLookup::Anywhere,
+            EnvironmentScope::Global,
+        )?;
+    }
+
+    open_files.files.insert(fd_num, file);
+    open_files.set_close_on_exec(fd_num, false);
+    Ok(())
+}
+
+/// Retrieves the file descriptor number stored in the given variable.
+fn get_fd_from_var(shell: &Shell, name: &str) -> Result<u32, error::Error> {
+    shell
+        .env
+        .get(name)
+        .and_then(|(_, var)| var.value().to_cow_string().parse().ok())
+        .ok_or(error::Error::InvalidRedirection)
+}
+
 fn get_default_fd_for_redirect_kind(kind: &ast::IoFileRedirectKind) -> u32 {
     match kind {
         ast::IoFileRedirectKind::Read => 0,
@@ -1458,7 +2284,7 @@ fn setup_process_substitution(
 ) -> Result<(u32, OpenFile), error::Error> {
     // TODO: Don't execute synchronously!
     // Execute in a subshell.
-    let mut subshell = shell.clone();
+    let mut subshell = shell.new_subshell();
 
     // Set up pipe so we can connect to the command.
     let (reader, writer) = sys::pipes::pipe()?;
@@ -1491,8 +2317,15 @@ fn setup_process_substitution(
     tokio::spawn(async move {
         // Intentionally ignore the result of the subshell command.
         let _ = subshell_cmd.0.execute(&mut subshell, &exec_params).await;
+        let _ = subshell.run_exit_trap_handler(&exec_params).await;
     });
 
+    let fd_num = find_available_fd(open_files)?;
+
+    Ok((fd_num, target_file))
+}
+
+fn find_available_fd(open_files: &OpenFiles) -> Result<u32, error::Error> {
     // Starting at 63 (a.k.a. 64-1)--and decrementing--look for an
     // available fd.
     let mut candidate_fd_num = 63;
@@ -1503,7 +2336,7 @@ fn setup_process_substitution(
         }
     }
 
-    Ok((candidate_fd_num, target_file))
+    Ok(candidate_fd_num)
 }
 
 #[allow(unused_variables)]
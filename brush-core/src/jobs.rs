@@ -255,6 +255,9 @@ pub struct Job {
 
     /// The current operational state of the job.
     pub state: JobState,
+
+    /// If the job is a coprocess, the name it was given.
+    coproc_name: Option<String>,
 }
 
 impl Display for Job {
@@ -289,9 +292,24 @@ impl Job {
             annotation: JobAnnotation::None,
             command_line,
             state,
+            coproc_name: None,
         }
     }
 
+    /// Marks the job as a coprocess with the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the coprocess.
+    pub(crate) fn set_coproc_name(&mut self, name: &str) {
+        self.coproc_name = Some(name.to_owned());
+    }
+
+    /// Returns the name of the coprocess, if the job is one.
+    pub(crate) fn get_coproc_name(&self) -> Option<&str> {
+        self.coproc_name.as_deref()
+    }
+
     /// Returns a pid-style string for the job.
     pub fn to_pid_style_string(&self) -> String {
         let display_pid = self
@@ -29,13 +29,14 @@ mod shell;
 mod sys;
 mod terminal;
 mod tests;
+mod timing;
 mod trace_categories;
 mod traps;
 mod variables;
 
 pub use commands::ExecutionContext;
-pub use error::Error;
+pub use error::{Error, ErrorContext};
 pub use interp::{ExecutionParameters, ExecutionResult};
-pub use shell::{CreateOptions, Shell};
+pub use shell::{CreateOptions, LineEditorFn, Shell};
 pub use terminal::TerminalControl;
-pub use variables::{ShellValue, ShellVariable};
+pub use variables::{DynamicValue, RandomNumberGenerator, ShellValue, ShellVariable};
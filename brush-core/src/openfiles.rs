@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::IsTerminal;
 #[cfg(unix)]
 use std::os::fd::AsFd;
@@ -94,6 +94,23 @@ impl OpenFile {
         }
     }
 
+    /// Waits up to the given duration for input to become available to read from
+    /// the open file. Returns whether or not input is available.
+    pub(crate) fn poll_for_input(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<bool, error::Error> {
+        match self {
+            OpenFile::Stdin => sys::fs::poll_for_stdin_input(timeout),
+            OpenFile::Stdout => Err(error::Error::OpenFileNotReadable("stdout")),
+            OpenFile::Stderr => Err(error::Error::OpenFileNotReadable("stderr")),
+            OpenFile::Null => Ok(true),
+            OpenFile::File(f) => sys::fs::poll_for_input(f, timeout),
+            OpenFile::PipeReader(r) => sys::fs::poll_for_input(r, timeout),
+            OpenFile::PipeWriter(_) => Err(error::Error::OpenFileNotReadable("pipe writer")),
+        }
+    }
+
     pub(crate) fn get_term_attr(
         &self,
     ) -> Result<Option<sys::terminal::TerminalSettings>, error::Error> {
@@ -209,6 +226,12 @@ impl std::io::Write for OpenFile {
 pub struct OpenFiles {
     /// Maps shell file descriptors to open files.
     pub files: HashMap<u32, OpenFile>,
+    /// Counter incremented with each persistent change to the open files.
+    generation: u64,
+    /// Maps file descriptors to the generation in which they were last persistently changed.
+    persistent_changes: HashMap<u32, u64>,
+    /// File descriptors that aren't inherited by external commands spawned by the shell.
+    close_on_exec: HashSet<u32>,
 }
 
 impl Clone for OpenFiles {
@@ -225,6 +248,9 @@ impl Default for OpenFiles {
                 (1, OpenFile::Stdout),
                 (2, OpenFile::Stderr),
             ]),
+            generation: 0,
+            persistent_changes: HashMap::new(),
+            close_on_exec: HashSet::new(),
         }
     }
 }
@@ -237,7 +263,87 @@ impl OpenFiles {
             files.insert(*fd, file.try_dup()?);
         }
 
-        Ok(OpenFiles { files })
+        Ok(OpenFiles {
+            files,
+            generation: self.generation,
+            persistent_changes: self.persistent_changes.clone(),
+            close_on_exec: self.close_on_exec.clone(),
+        })
+    }
+
+    /// Persistently sets the file open as the given file descriptor, or closes the
+    /// descriptor if no file is provided. Unlike direct updates to `files`, these
+    /// changes are carried over by `try_update_from` to the open files of contexts
+    /// derived from this one before the change was made.
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - The file descriptor to update.
+    /// * `file` - The file to open as the descriptor, if any.
+    pub(crate) fn set_persistent(&mut self, fd: u32, file: Option<OpenFile>) {
+        if let Some(file) = file {
+            self.files.insert(fd, file);
+        } else {
+            self.files.remove(&fd);
+        }
+
+        self.close_on_exec.remove(&fd);
+
+        self.generation += 1;
+        self.persistent_changes.insert(fd, self.generation);
+    }
+
+    /// Tries to apply persistent changes made to `source` since these open files were
+    /// derived from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The open files these were derived from.
+    pub(crate) fn try_update_from(&mut self, source: &OpenFiles) -> Result<(), error::Error> {
+        for (fd, generation) in &source.persistent_changes {
+            if *generation <= self.generation {
+                continue;
+            }
+
+            if let Some(file) = source.files.get(fd) {
+                self.files.insert(*fd, file.try_dup()?);
+            } else {
+                self.files.remove(fd);
+            }
+
+            self.set_close_on_exec(*fd, source.is_close_on_exec(*fd));
+
+            self.persistent_changes.insert(*fd, *generation);
+        }
+
+        self.generation = self.generation.max(source.generation);
+
+        Ok(())
+    }
+
+    /// Sets whether the given file descriptor is kept from external commands spawned
+    /// by the shell. Files newly opened as a descriptor are inherited by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - The file descriptor to update.
+    /// * `close_on_exec` - Whether the descriptor should be kept from external commands.
+    pub(crate) fn set_close_on_exec(&mut self, fd: u32, close_on_exec: bool) {
+        if close_on_exec {
+            self.close_on_exec.insert(fd);
+        } else {
+            self.close_on_exec.remove(&fd);
+        }
+    }
+
+    /// Returns whether the given file descriptor is kept from external commands
+    /// spawned by the shell.
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - The file descriptor to check.
+    pub(crate) fn is_close_on_exec(&self, fd: u32) -> bool {
+        self.close_on_exec.contains(&fd)
     }
 
     /// Retrieves the file backing standard input in this context.
@@ -1,6 +1,5 @@
 use std::borrow::Cow;
 use std::collections::{HashMap, VecDeque};
-use std::fmt::Write as _;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -10,7 +9,7 @@ use crate::env::{EnvironmentLookup, EnvironmentScope, ShellEnvironment};
 use crate::interp::{self, Execute, ExecutionParameters, ExecutionResult};
 use crate::options::RuntimeOptions;
 use crate::sys::fs::PathExt;
-use crate::variables::{self, ShellValue, ShellVariable};
+use crate::variables::{self, DynamicValue, RandomNumberGenerator, ShellValue, ShellVariable};
 use crate::{
     builtins, commands, completion, env, error, expansion, functions, jobs, keywords, openfiles,
     patterns, prompt, sys::users, traps,
@@ -70,6 +69,16 @@ pub struct Shell {
     /// Current line number being processed.
     pub current_line_number: u32,
 
+    /// Number of lines of input run so far via `run_string`.
+    input_line_count: u32,
+
+    /// Depth of nested contexts (e.g., `if` conditions or non-final commands in an
+    /// and/or list) in which the errexit (`set -e`) option is being ignored.
+    pub(crate) errexit_ignore_depth: usize,
+
+    /// Number of times `PIPESTATUS` has been updated by a completed pipeline.
+    pub(crate) pipestatus_update_count: usize,
+
     /// Completion configuration.
     pub completion_config: completion::Config,
 
@@ -78,6 +87,16 @@ pub struct Shell {
 
     /// Shell program location cache.
     pub program_location_cache: pathcache::PathCache,
+
+    /// Time at which the shell was started.
+    pub(crate) start_time: std::time::SystemTime,
+
+    /// Optionally provides an interactive line editor for reading input (e.g., `read -e`).
+    pub line_editor: Option<LineEditorFn>,
+
+    /// In a subshell, the resource limits set by the subshell, which are applied to the
+    /// external commands it runs rather than to the whole process.
+    pub(crate) subshell_resource_limits: Option<crate::sys::resource::ResourceLimits>,
 }
 
 impl Clone for Shell {
@@ -99,9 +118,15 @@ impl Clone for Shell {
             script_call_stack: self.script_call_stack.clone(),
             directory_stack: self.directory_stack.clone(),
             current_line_number: self.current_line_number,
+            input_line_count: self.input_line_count,
+            errexit_ignore_depth: self.errexit_ignore_depth,
+            pipestatus_update_count: self.pipestatus_update_count,
             completion_config: self.completion_config.clone(),
             builtins: self.builtins.clone(),
             program_location_cache: self.program_location_cache.clone(),
+            start_time: self.start_time,
+            line_editor: self.line_editor,
+            subshell_resource_limits: self.subshell_resource_limits.clone(),
             depth: self.depth + 1,
             user_tried_exiting: self.user_tried_exiting
         }
@@ -120,6 +145,13 @@ impl AsMut<Shell> for Shell {
     }
 }
 
+/// Signature of a function that reads a line of input from the terminal using an
+/// interactive line editor. It is given the prompt to display and the text to
+/// pre-fill the line with; it returns the line read, or `None` at end of input. If
+/// reading is interrupted (e.g., by Ctrl+C), it returns `Error::Interrupted`.
+pub type LineEditorFn =
+    fn(prompt: &str, initial_text: &str) -> Result<Option<String>, error::Error>;
+
 /// Options for creating a new shell.
 #[derive(Debug, Default)]
 pub struct CreateOptions {
@@ -164,6 +196,11 @@ pub struct FunctionCall {
     function_name: String,
     /// The definition of the invoked function.
     function_definition: Arc<brush_parser::ast::FunctionDefinition>,
+    /// The line number from which the function was invoked.
+    call_line_number: u32,
+    /// Trap signals whose handlers aren't inherited by the function, and so are suspended
+    /// for the duration of the call.
+    suspended_traps: Vec<traps::TrapSignal>,
 }
 
 impl Shell {
@@ -191,9 +228,15 @@ impl Shell {
             script_call_stack: VecDeque::new(),
             directory_stack: vec![],
             current_line_number: 0,
+            input_line_count: 0,
+            errexit_ignore_depth: 0,
+            pipestatus_update_count: 0,
             completion_config: completion::Config::default(),
             builtins: builtins::get_default_builtins(options),
             program_location_cache: pathcache::PathCache::default(),
+            start_time: std::time::SystemTime::now(),
+            line_editor: None,
+            subshell_resource_limits: None,
             depth: 0,
             user_tried_exiting: 0
         };
@@ -202,6 +245,9 @@ impl Shell {
         // parse the entire script with the same settings.
         shell.options.extended_globbing = true;
 
+        // Make sure options given at startup are reflected in the environment.
+        shell.apply_options_to_env();
+
         // Load profiles/configuration.
         shell.load_config(options).await?;
 
@@ -229,12 +275,64 @@ impl Shell {
             )));
             euid_var.set_readonly();
             env.set_global("EUID", euid_var)?;
+
+            let mut uid_var =
+                ShellVariable::new(ShellValue::String(uzers::get_current_uid().to_string()));
+            uid_var.set_readonly();
+            env.set_global("UID", uid_var)?;
+
+            let mut ppid_var = ShellVariable::new(ShellValue::String(
+                std::os::unix::process::parent_id().to_string(),
+            ));
+            ppid_var.set_readonly();
+            env.set_global("PPID", ppid_var)?;
+        }
+
+        // Dynamic vars, whose values are computed each time they're read.
+        for (name, value, treat_as_integer) in [
+            (
+                "RANDOM",
+                DynamicValue::Random(RandomNumberGenerator::default()),
+                true,
+            ),
+            ("SRANDOM", DynamicValue::SecureRandom, true),
+            ("SECONDS", DynamicValue::seconds(), true),
+            ("EPOCHSECONDS", DynamicValue::EpochSeconds, false),
+            ("EPOCHREALTIME", DynamicValue::EpochRealTime, false),
+            ("BASHPID", DynamicValue::ProcessId(None), true),
+            ("HISTCMD", DynamicValue::HistoryNumber(1), true),
+            ("LINENO", DynamicValue::LineNumber(0), false),
+        ] {
+            let mut var = ShellVariable::new(ShellValue::Dynamic(value));
+            var.hide_from_enumeration();
+            if treat_as_integer {
+                var.treat_as_integer();
+            }
+            env.set_global(name, var)?;
+        }
+
+        // Track how deeply nested this shell is.
+        let shell_level = env
+            .get_str("SHLVL")
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .unwrap_or(0)
+            .max(0)
+            + 1;
+        let mut shlvl_var = ShellVariable::new(shell_level.to_string().into());
+        shlvl_var.export();
+        env.set_global("SHLVL", shlvl_var)?;
+
+        if let Ok(hostname) = crate::sys::network::get_hostname() {
+            env.set_global(
+                "HOSTNAME",
+                ShellVariable::new(hostname.to_string_lossy().to_string().into()),
+            )?;
         }
 
-        let mut random_var = ShellVariable::new(ShellValue::Random);
-        random_var.hide_from_enumeration();
-        random_var.treat_as_integer();
-        env.set_global("RANDOM", random_var)?;
+        // Tracing vars
+        if !env.is_set("PS4") {
+            env.set_global("PS4", ShellVariable::new("+ ".into()))?;
+        }
 
         // Parsing and completion vars
         env.set_global("IFS", ShellVariable::new(" \t\n".into()))?;
@@ -436,6 +534,7 @@ impl Shell {
 
         let source_info = brush_parser::SourceInfo {
             source: path.to_string_lossy().to_string(),
+            line_offset: 0,
         };
 
         self.source_file(opened_file, &source_info, args, params)
@@ -458,11 +557,8 @@ impl Shell {
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
         let mut reader = std::io::BufReader::new(file);
-        let mut parser =
-            brush_parser::Parser::new(&mut reader, &self.parser_options(), source_info);
 
         tracing::debug!(target: trace_categories::PARSE, "Parsing sourced file: {}", source_info.source);
-        let parse_result = parser.parse();
 
         let mut other_positional_parameters = args.iter().map(|s| s.as_ref().to_owned()).collect();
         let mut other_shell_name = Some(source_info.source.clone());
@@ -478,9 +574,7 @@ impl Shell {
             .push_front(source_info.source.clone());
         self.update_bash_source_var()?;
 
-        let result = self
-            .run_parsed_result(parse_result, source_info, params)
-            .await;
+        let result = self.run_input(&mut reader, source_info, params).await;
 
         self.script_call_stack.pop_front();
         self.update_bash_source_var()?;
@@ -512,12 +606,13 @@ impl Shell {
             .get(name)
             .ok_or_else(|| error::Error::FunctionNotFound(name.to_owned()))?;
 
-        let func = func_registration.definition.clone();
+        let func = func_registration.clone();
 
         let context = commands::ExecutionContext {
             shell: self,
             command_name,
             params,
+            redirected_fds: vec![],
         };
 
         let command_args = args
@@ -540,6 +635,8 @@ impl Shell {
     }
 
     /// Executes the given string as a shell program, returning the resulting exit status.
+    /// The string is treated as the next lines of the shell's input, following those
+    /// most recently run.
     ///
     /// # Arguments
     ///
@@ -550,19 +647,314 @@ impl Shell {
         command: String,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        // TODO: Actually track line numbers; this is something of a hack, assuming each time
-        // this function is invoked we are on the next line of the input. For one thing,
-        // each string we run could be multiple lines.
-        self.current_line_number += 1;
+        let line_offset = self.input_line_count;
+        self.input_line_count = self
+            .input_line_count
+            .saturating_add(u32::try_from(command.lines().count()).unwrap_or(u32::MAX));
 
-        let parse_result = self.parse_string(command);
         let source_info = brush_parser::SourceInfo {
             source: String::from("main"),
+            line_offset: i32::try_from(line_offset).unwrap_or(i32::MAX),
         };
-        self.run_parsed_result(parse_result, &source_info, params)
+
+        self.run_input(&mut command.as_bytes(), &source_info, params)
+            .await
+    }
+
+    /// Executes the given string as a shell program nested within the command currently
+    /// being executed (e.g., for `eval` or a trap handler), returning the resulting exit
+    /// status. The first line of the string is treated as the current line.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to execute.
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_nested_string(
+        &mut self,
+        command: String,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let line_offset = self.current_line_number.saturating_sub(1);
+        self.run_string_at_line_offset(command, line_offset, params)
             .await
     }
 
+    async fn run_string_at_line_offset(
+        &mut self,
+        command: String,
+        line_offset: u32,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let source_info = brush_parser::SourceInfo {
+            source: String::from("main"),
+            line_offset: i32::try_from(line_offset).unwrap_or(i32::MAX),
+        };
+        let parse_result = parse_string_impl(command, self.parser_options(), &source_info);
+
+        self.run_parsed_result(parse_result, params).await
+    }
+
+    /// Runs shell input read from the given reader one complete command at a time. Lines
+    /// are only read as needed to complete the next command, and are echoed as they're
+    /// read if `set -v` is in effect; this way, options changed by a command apply to the
+    /// lines that follow it.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader to read input from.
+    /// * `source_info` - Information about the source of the input.
+    /// * `params` - Execution parameters.
+    async fn run_input<R: std::io::BufRead>(
+        &mut self,
+        reader: &mut R,
+        source_info: &brush_parser::SourceInfo,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let mut line_offset = source_info.line_offset;
+        let mut result = None;
+
+        loop {
+            let command_source_info = brush_parser::SourceInfo {
+                source: source_info.source.clone(),
+                line_offset,
+            };
+
+            let mut command = String::new();
+            let mut line_count: usize = 0;
+            let (parse_result, more_input) = loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? > 0 {
+                    if self.options.print_shell_input_lines {
+                        self.echo_shell_input(line.as_str())?;
+                    }
+                    command.push_str(line.as_str());
+                    line_count += 1;
+                }
+
+                let more_input = !reader.fill_buf()?.is_empty();
+
+                // Only try parsing a command spanning several lines once its line count
+                // reaches each power of two; this keeps the cost of re-parsing it linear.
+                // Any complete commands that follow it on those lines run along with it.
+                if more_input
+                    && (ends_with_line_continuation(command.as_str())
+                        || !line_count.is_power_of_two())
+                {
+                    continue;
+                }
+
+                let parse_result =
+                    parse_string_impl(command.clone(), self.parser_options(), &command_source_info);
+                if !more_input || !needs_more_input(&parse_result) {
+                    break (parse_result, more_input);
+                }
+            };
+
+            line_offset = line_offset.saturating_add(i32::try_from(line_count).unwrap_or(i32::MAX));
+
+            // Make sure trailing blank lines or comments don't reset the last exit status.
+            if !more_input
+                && result.is_some()
+                && matches!(&parse_result, Ok(program) if program.complete_commands.is_empty())
+            {
+                break;
+            }
+
+            let parse_failed = parse_result.is_err();
+            let command_result = self.run_parsed_result(parse_result, params).await?;
+
+            let stop = parse_failed
+                || command_result.exit_shell
+                || command_result.return_from_function_or_script;
+            result = Some(command_result);
+
+            if stop || !more_input {
+                break;
+            }
+        }
+
+        Ok(result.unwrap_or_else(ExecutionResult::success))
+    }
+
+    /// Runs the given trap handler command. The last exit status observed before running the
+    /// handler is restored afterwards, unless the handler causes the shell to exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The handler command to run.
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_trap_handler(
+        &mut self,
+        handler: &str,
+        params: &ExecutionParameters,
+    ) -> Result<ExecutionResult, error::Error> {
+        let prior_exit_status = self.last_exit_status;
+        let prior_line_number = self.current_line_number;
+        let prior_pipestatus_update_count = self.pipestatus_update_count;
+
+        self.traps.handler_depth += 1;
+        let result = self.run_nested_string(handler.to_owned(), params).await;
+        self.traps.handler_depth -= 1;
+
+        self.set_current_line_number(prior_line_number);
+
+        // Pipelines run by the handler mustn't stop the interrupted command from
+        // updating PIPESTATUS.
+        self.pipestatus_update_count = prior_pipestatus_update_count;
+
+        let result = result?;
+        if !result.exit_shell {
+            self.last_exit_status = prior_exit_status;
+        }
+
+        Ok(result)
+    }
+
+    /// Runs the handlers for any trapped signals received since the last time this was
+    /// checked. Returns the result of the handler that caused the shell to exit, if any did.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_pending_signal_trap_handlers(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<Option<ExecutionResult>, error::Error> {
+        // Signals received while a trap handler is running are handled once it completes.
+        if self.traps.handler_depth > 0 {
+            return Ok(None);
+        }
+
+        #[allow(unused_variables)] // on not unix platforms
+        let pending_handlers = self
+            .traps
+            .handlers
+            .iter()
+            .filter_map(|(signal_type, handler)| match signal_type {
+                #[cfg(unix)]
+                traps::TrapSignal::Signal(signal)
+                    if !handler.is_empty()
+                        && crate::sys::signal::take_pending_trapped_signal(*signal) =>
+                {
+                    Some(handler.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for handler in pending_handlers {
+            let result = self.run_trap_handler(handler.as_str(), params).await?;
+            if result.exit_shell {
+                return Ok(Some(result));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs the handler registered for the given trap signal, if any. Returns the handler's
+    /// result if it caused the shell to exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal_type` - The trap signal whose handler should be run.
+    /// * `params` - Execution parameters.
+    pub(crate) async fn run_registered_trap_handler(
+        &mut self,
+        signal_type: traps::TrapSignal,
+        params: &ExecutionParameters,
+    ) -> Result<Option<ExecutionResult>, error::Error> {
+        if self.traps.handler_depth > 0 {
+            return Ok(None);
+        }
+
+        let handler = self.get_trap_handler(signal_type).cloned();
+        let Some(handler) = handler.filter(|handler| !handler.is_empty()) else {
+            return Ok(None);
+        };
+
+        let result = self.run_trap_handler(handler.as_str(), params).await?;
+
+        Ok(result.exit_shell.then_some(result))
+    }
+
+    /// Returns the handler registered for the given trap signal, unless it's suspended
+    /// by a function call in progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal_type` - The trap signal to look up.
+    pub(crate) fn get_trap_handler(&self, signal_type: traps::TrapSignal) -> Option<&String> {
+        if self.is_trap_suspended(signal_type) {
+            None
+        } else {
+            self.traps.handlers.get(&signal_type)
+        }
+    }
+
+    /// Registers a handler for a trap signal. A handler registered while a function call
+    /// has suspended the signal's handler takes effect immediately, and remains in effect
+    /// once the call returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal_type` - The trap signal to register a handler for.
+    /// * `command` - The command to execute when the signal is trapped.
+    pub(crate) fn register_trap_handler(
+        &mut self,
+        signal_type: traps::TrapSignal,
+        command: String,
+    ) -> Result<(), error::Error> {
+        for call in &mut self.function_call_stack {
+            call.suspended_traps
+                .retain(|suspended| *suspended != signal_type);
+        }
+
+        self.traps.register_handler(signal_type, command)
+    }
+
+    /// Removes the handlers for a trap signal. As in bash, a handler suspended by a function
+    /// call in progress is unaffected, and is back in effect once the call returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal_type` - The trap signal whose handlers should be removed.
+    pub(crate) fn remove_trap_handlers(
+        &mut self,
+        signal_type: traps::TrapSignal,
+    ) -> Result<(), error::Error> {
+        if self.is_trap_suspended(signal_type) {
+            return Ok(());
+        }
+
+        self.traps.remove_handlers(signal_type)
+    }
+
+    fn is_trap_suspended(&self, signal_type: traps::TrapSignal) -> bool {
+        self.function_call_stack
+            .iter()
+            .any(|call| call.suspended_traps.contains(&signal_type))
+    }
+
+    /// Runs the `EXIT` trap handler, if one is registered. Should be invoked when the shell
+    /// (or a subshell) is about to exit; the handler runs at most once.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - Execution parameters.
+    pub async fn run_exit_trap_handler(
+        &mut self,
+        params: &ExecutionParameters,
+    ) -> Result<(), error::Error> {
+        if let Some(handler) = self.traps.handlers.remove(&traps::TrapSignal::Exit) {
+            if !handler.is_empty() {
+                self.run_trap_handler(handler.as_str(), params).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Parses the given string as a shell program, returning the resulting Abstract Syntax Tree
     /// for the program.
     ///
@@ -573,7 +965,11 @@ impl Shell {
         &self,
         s: String,
     ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
-        parse_string_impl(s, self.parser_options())
+        parse_string_impl(
+            s,
+            self.parser_options(),
+            &brush_parser::SourceInfo::default(),
+        )
     }
 
     /// Applies basic shell expansion to the provided string.
@@ -629,56 +1025,47 @@ impl Shell {
     async fn run_parsed_result(
         &mut self,
         parse_result: Result<brush_parser::ast::Program, brush_parser::ParseError>,
-        source_info: &brush_parser::SourceInfo,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let mut error_prefix = String::new();
-
-        if !source_info.source.is_empty() {
-            error_prefix = format!("{}: ", source_info.source);
-        }
-
         let result = match parse_result {
             Ok(prog) => match self.run_program(prog, params).await {
                 Ok(result) => result,
                 Err(e) => {
-                    tracing::error!("error: {:#}", e);
+                    self.report_error(&e, params)?;
                     self.last_exit_status = 1;
-                    ExecutionResult::new(1)
+
+                    let mut result = ExecutionResult::new(1);
+                    if !self.options.interactive && e.is_fatal_to_non_interactive_shell() {
+                        result.exit_shell = true;
+                    }
+
+                    result
                 }
             },
-            Err(brush_parser::ParseError::ParsingNearToken(token_near_error)) => {
-                let error_loc = &token_near_error.location().start;
-
-                tracing::error!(
-                    "{}syntax error near token `{}' (line {} col {})",
-                    error_prefix,
-                    token_near_error.to_str(),
-                    error_loc.line,
-                    error_loc.column,
-                );
-                self.last_exit_status = 2;
-                ExecutionResult::new(2)
-            }
-            Err(brush_parser::ParseError::ParsingAtEndOfInput) => {
-                tracing::error!("{}syntax error at end of input", error_prefix);
-
-                self.last_exit_status = 2;
-                ExecutionResult::new(2)
-            }
-            Err(brush_parser::ParseError::Tokenizing { inner, position }) => {
-                let mut error_message = error_prefix.clone();
-                error_message.push_str(inner.to_string().as_str());
-
-                if let Some(position) = position {
-                    write!(
-                        error_message,
-                        " (detected near line {} column {})",
-                        position.line, position.column
-                    )?;
+            Err(parse_error) => {
+                let (error, error_line) = match parse_error {
+                    brush_parser::ParseError::ParsingNearToken(token_near_error) => (
+                        error::Error::SyntaxErrorNearToken(token_near_error.to_str().to_owned()),
+                        Some(token_near_error.location().start.line),
+                    ),
+                    brush_parser::ParseError::ParsingAtEndOfInput => {
+                        (error::Error::SyntaxErrorAtEndOfInput, None)
+                    }
+                    brush_parser::ParseError::Tokenizing { inner, position } => (
+                        error::Error::TokenizingError(inner.to_string()),
+                        position.map(|position| position.line),
+                    ),
+                };
+
+                // Syntax errors are reported at the line they were detected on.
+                let mut context = self.error_context();
+                if context.line_number.is_some() {
+                    if let Some(error_line) = error_line.and_then(|line| u32::try_from(line).ok()) {
+                        context.line_number = Some(error_line);
+                    }
                 }
 
-                tracing::error!("{}", error_message);
+                self.report_error(&error::Error::WithContext(context, Box::new(error)), params)?;
 
                 self.last_exit_status = 2;
                 ExecutionResult::new(2)
@@ -795,6 +1182,12 @@ impl Shell {
         cs.into_iter().collect()
     }
 
+    /// Propagates the current values of runtime options that affect variable handling
+    /// to the shell's variable environment. Must be called after any such option changes.
+    pub(crate) fn apply_options_to_env(&mut self) {
+        self.env.export_variables_on_modification = self.options.export_variables_on_modification;
+    }
+
     /// Returns the options that should be used for parsing shell programs; reflects
     /// the current configuration state of the shell and may change over time.
     pub fn parser_options(&self) -> brush_parser::ParserOptions {
@@ -822,10 +1215,13 @@ impl Shell {
         &mut self,
         name: &str,
         function_def: &Arc<brush_parser::ast::FunctionDefinition>,
+        suspended_traps: Vec<traps::TrapSignal>,
     ) -> Result<(), error::Error> {
         self.function_call_stack.push_front(FunctionCall {
             function_name: name.to_owned(),
             function_definition: function_def.clone(),
+            call_line_number: self.current_line_number,
+            suspended_traps,
         });
         self.env.push_scope(env::EnvironmentScope::Local);
         self.update_funcname_var()?;
@@ -851,10 +1247,25 @@ impl Shell {
             .map(|s| (None, s.function_name.clone()))
             .collect::<Vec<_>>();
 
-        self.env.update_or_add(
+        self.env.update_or_add_shell_managed(
             "FUNCNAME",
             variables::ShellValueLiteral::Array(variables::ArrayLiteral(funcname_values)),
-            |_| Ok(()),
+            EnvironmentLookup::Anywhere,
+            EnvironmentScope::Global,
+        )?;
+
+        //
+        // Fill out BASH_LINENO[*]
+        //
+        let lineno_values = self
+            .function_call_stack
+            .iter()
+            .map(|s| (None, s.call_line_number.to_string()))
+            .collect::<Vec<_>>();
+
+        self.env.update_or_add_shell_managed(
+            "BASH_LINENO",
+            variables::ShellValueLiteral::Array(variables::ArrayLiteral(lineno_values)),
             EnvironmentLookup::Anywhere,
             EnvironmentScope::Global,
         )?;
@@ -877,10 +1288,9 @@ impl Shell {
                 .collect::<Vec<_>>()
         };
 
-        self.env.update_or_add(
+        self.env.update_or_add_shell_managed(
             "BASH_SOURCE",
             variables::ShellValueLiteral::Array(variables::ArrayLiteral(source_values)),
-            |_| Ok(()),
             EnvironmentLookup::Anywhere,
             EnvironmentScope::Global,
         )?;
@@ -901,6 +1311,131 @@ impl Shell {
         self.current_line_number
     }
 
+    /// Returns a description of the point in the shell's input currently being executed,
+    /// for use in reporting errors.
+    pub(crate) fn error_context(&self) -> error::ErrorContext {
+        let source = self
+            .script_call_stack
+            .front()
+            .or(self.shell_name.as_ref())
+            .cloned()
+            .unwrap_or_default();
+
+        // Interactive shells only report line numbers for the scripts they run.
+        let line_number = if self.options.interactive && self.script_call_stack.is_empty() {
+            None
+        } else {
+            Some(self.get_current_input_line_number())
+        };
+
+        error::ErrorContext {
+            source,
+            function_name: self
+                .function_call_stack
+                .front()
+                .map(|call| call.function_name.clone()),
+            line_number,
+        }
+    }
+
+    /// Annotates the given error with the point in the shell's input currently being
+    /// executed, unless it's already annotated.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error to annotate.
+    pub(crate) fn add_error_context(&self, error: error::Error) -> error::Error {
+        if matches!(error, error::Error::WithContext(..)) {
+            error
+        } else {
+            error::Error::WithContext(self.error_context(), Box::new(error))
+        }
+    }
+
+    /// Reports the given error to the user on standard error, in bash's format or, with
+    /// `gnu_errfmt` enabled, in GNU's `file:line:` format.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error to report.
+    /// * `params` - Execution parameters providing the standard error to report on.
+    pub(crate) fn report_error(
+        &self,
+        error: &error::Error,
+        params: &ExecutionParameters,
+    ) -> Result<(), error::Error> {
+        self.report_error_on(error, &params.open_files)
+    }
+
+    /// Reports the given error to the user on the standard error of the given set of
+    /// open files; otherwise the same as `report_error`.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error to report.
+    /// * `open_files` - The open files providing the standard error to report on.
+    pub(crate) fn report_error_on(
+        &self,
+        error: &error::Error,
+        open_files: &openfiles::OpenFiles,
+    ) -> Result<(), error::Error> {
+        if let Some(stderr) = open_files.stderr() {
+            writeln!(
+                stderr.try_dup()?,
+                "{}",
+                error.format_with_context(&self.error_context(), self.options.errors_in_gnu_format)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the given condition to the user as a warning on the standard error of
+    /// the given set of open files, in the same format used for errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `warning` - The condition to warn about.
+    /// * `open_files` - The open files providing the standard error to report on.
+    pub(crate) fn report_warning_on(
+        &self,
+        warning: &error::Error,
+        open_files: &openfiles::OpenFiles,
+    ) -> Result<(), error::Error> {
+        if let Some(stderr) = open_files.stderr() {
+            writeln!(
+                stderr.try_dup()?,
+                "{}",
+                self.error_context().format_message(
+                    std::format!("warning: {warning}").as_str(),
+                    self.options.errors_in_gnu_format
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates the number of the line being executed, reflecting it in `LINENO`.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_number` - The number of the line now being executed.
+    pub(crate) fn set_current_line_number(&mut self, line_number: u32) {
+        self.current_line_number = line_number;
+
+        // Only the shell's own LINENO tracks the line; a local variable of the same name
+        // shadowing it is left alone.
+        if let Some(var) = self
+            .env
+            .get_mut_using_policy_ignoring_nameref("LINENO", EnvironmentLookup::OnlyInGlobal)
+        {
+            if let Some(DynamicValue::LineNumber(current)) = var.dynamic_value_mut() {
+                *current = line_number;
+            }
+        }
+    }
+
     /// Returns the current value of the IFS variable, or the default value if it is not set.
     pub(crate) fn get_ifs(&self) -> Cow<'_, str> {
         self.env.get("IFS").map_or_else(
@@ -1138,12 +1673,18 @@ impl Shell {
     /// # Arguments
     ///
     /// * `command` - The command to trace.
-    pub(crate) fn trace_command<S: AsRef<str>>(&self, command: S) -> Result<(), std::io::Error> {
-        // TODO: get prefix from PS4
-        const DEFAULT_PREFIX: &str = "+ ";
-
-        let mut prefix = DEFAULT_PREFIX.to_owned();
-
+    pub(crate) async fn trace_command<S: AsRef<str>>(
+        &mut self,
+        command: S,
+    ) -> Result<(), error::Error> {
+        // Expand PS4, making sure we don't end up tracing anything run during its expansion.
+        let prior_trace_setting = self.options.print_commands_and_arguments;
+        self.options.print_commands_and_arguments = false;
+        let prefix = self.prompt_from_var_or_default("PS4", "").await;
+        self.options.print_commands_and_arguments = prior_trace_setting;
+        let mut prefix = prefix?;
+
+        // The first character of the prefix is repeated to indicate nesting depth.
         let additional_depth = self.script_call_stack.len() + self.depth;
         if let Some(c) = prefix.chars().next() {
             for _ in 0..additional_depth {
@@ -1151,7 +1692,37 @@ impl Shell {
             }
         }
 
-        writeln!(self.stderr(), "{prefix}{}", command.as_ref())
+        writeln!(self.xtrace_file(), "{prefix}{}", command.as_ref())?;
+
+        Ok(())
+    }
+
+    /// Returns the file that `set -x` style trace output should be written to; this is
+    /// standard error unless `BASH_XTRACEFD` names another open file descriptor.
+    fn xtrace_file(&self) -> openfiles::OpenFile {
+        let xtrace_file = self
+            .env
+            .get_str("BASH_XTRACEFD")
+            .and_then(|fd| fd.trim().parse::<u32>().ok())
+            .and_then(|fd| self.open_files.files.get(&fd))
+            .and_then(|file| file.try_dup().ok());
+
+        xtrace_file.unwrap_or_else(|| self.stderr())
+    }
+
+    /// Echoes the given shell input to standard error, as is done with `set -v`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input to echo.
+    fn echo_shell_input(&self, input: &str) -> Result<(), error::Error> {
+        let mut stderr = self.stderr();
+        write!(stderr, "{input}")?;
+        if !input.is_empty() && !input.ends_with('\n') {
+            writeln!(stderr)?;
+        }
+
+        Ok(())
     }
 
     /// Returns the keywords that are reserved by the shell.
@@ -1180,6 +1751,10 @@ impl Shell {
     pub fn check_for_completed_jobs(&mut self) -> Result<(), error::Error> {
         let results = self.jobs.poll()?;
 
+        for (job, _result) in &results {
+            self.on_job_completed(job)?;
+        }
+
         if self.options.interactive {
             for (job, _result) in results {
                 writeln!(self.stderr(), "{job}")?;
@@ -1189,6 +1764,58 @@ impl Shell {
         Ok(())
     }
 
+    /// Cleans up after a job that's completed. Once a coprocess is reaped, the
+    /// variables describing it are unset.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The completed job.
+    pub(crate) fn on_job_completed(&mut self, job: &jobs::Job) -> Result<(), error::Error> {
+        if let Some(name) = job.get_coproc_name() {
+            self.env.unset(name)?;
+            self.env.unset(std::format!("{name}_PID").as_str())?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a subshell of this shell, to be run in the same process. Trap handlers that
+    /// a subshell doesn't inherit are reset, and the subshell's changes to signal
+    /// dispositions and resource limits are kept from affecting this shell.
+    pub(crate) fn new_subshell(&self) -> Self {
+        let mut subshell = self.clone();
+        subshell.traps.reset_for_subshell(&subshell.options);
+        subshell
+            .subshell_resource_limits
+            .get_or_insert_with(Default::default);
+
+        for name in ["RANDOM", "BASHPID"] {
+            if let Some(value) = subshell
+                .env
+                .get_mut(name)
+                .and_then(|(_, var)| var.dynamic_value_mut())
+            {
+                value.reset_for_subshell();
+            }
+        }
+
+        subshell
+    }
+
+    /// Updates the history number reported for the command being executed (`HISTCMD`).
+    /// Intended to be called by whatever owns the shell's command history.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The history number of the command about to be executed.
+    pub fn set_history_number(&mut self, number: usize) {
+        if let Some((_, var)) = self.env.get_mut("HISTCMD") {
+            if let Some(DynamicValue::HistoryNumber(current)) = var.dynamic_value_mut() {
+                *current = number;
+            }
+        }
+    }
+
     /// Evaluate the given arithmetic expression, returning the result.
     pub async fn eval_arithmetic(
         &mut self,
@@ -1199,17 +1826,44 @@ impl Shell {
     }
 }
 
-#[cached::proc_macro::cached(size = 64, result = true)]
+/// Checks whether the given result of parsing some input indicates that more input is
+/// needed to complete a command, either because the input is incomplete or because it
+/// contains no commands (e.g., only blank lines or comments).
+fn needs_more_input(
+    parse_result: &Result<brush_parser::ast::Program, brush_parser::ParseError>,
+) -> bool {
+    match parse_result {
+        Ok(program) => program.complete_commands.is_empty(),
+        Err(brush_parser::ParseError::Tokenizing { inner, .. }) => inner.is_incomplete(),
+        Err(brush_parser::ParseError::ParsingAtEndOfInput) => true,
+        Err(_) => false,
+    }
+}
+
+/// Checks whether the given input ends with an escaped newline, which continues its
+/// last line onto the next one.
+fn ends_with_line_continuation(input: &str) -> bool {
+    input
+        .strip_suffix('\n')
+        .is_some_and(|s| s.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1)
+}
+
+// N.B. The source info is folded into the cache key by value, so cached programs don't
+// carry line numbers from a different offset.
+#[cached::proc_macro::cached(
+    size = 64,
+    result = true,
+    key = "(String, brush_parser::ParserOptions, String, i32)",
+    convert = r#"{ (s.clone(), parser_options.clone(), source_info.source.clone(), source_info.line_offset) }"#
+)]
 fn parse_string_impl(
     s: String,
     parser_options: brush_parser::ParserOptions,
+    source_info: &brush_parser::SourceInfo,
 ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
     let mut reader = std::io::BufReader::new(s.as_bytes());
-    let source_info = brush_parser::SourceInfo {
-        source: String::from("main"),
-    };
     let mut parser: brush_parser::Parser<&mut std::io::BufReader<&[u8]>> =
-        brush_parser::Parser::new(&mut reader, &parser_options, &source_info);
+        brush_parser::Parser::new(&mut reader, &parser_options, source_info);
 
     tracing::debug!(target: trace_categories::PARSE, "Parsing string as program...");
     parser.parse()
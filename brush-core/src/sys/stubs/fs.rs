@@ -52,3 +52,18 @@ pub(crate) trait StubMetadataExt {
 }
 
 impl StubMetadataExt for std::fs::Metadata {}
+
+pub(crate) fn poll_for_input<Fd>(
+    _fd: Fd,
+    _timeout: std::time::Duration,
+) -> Result<bool, crate::error::Error> {
+    // Assume input is available; a subsequent read will block until it is.
+    Ok(true)
+}
+
+pub(crate) fn poll_for_stdin_input(
+    _timeout: std::time::Duration,
+) -> Result<bool, crate::error::Error> {
+    // Assume input is available; a subsequent read will block until it is.
+    Ok(true)
+}
@@ -41,6 +41,32 @@ pub(crate) fn mask_sigttou() -> Result<(), error::Error> {
     Ok(())
 }
 
+pub(crate) fn set_signal_trapped(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn set_signal_ignored(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+pub(crate) fn set_signal_default(_signal: traps::TrapSignal) -> Result<(), error::Error> {
+    Ok(())
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct SubshellSignalState {}
+
+impl SubshellSignalState {
+    #[allow(clippy::unused_self)]
+    pub(crate) fn save(&mut self, _signal: traps::TrapSignal) -> Result<(), error::Error> {
+        Ok(())
+    }
+}
+
+pub(crate) fn take_pending_trapped_signal(_signal: traps::TrapSignal) -> bool {
+    false
+}
+
 pub(crate) fn poll_for_stopped_children() -> Result<bool, error::Error> {
     Ok(false)
 }
@@ -1,6 +1,9 @@
+use std::os::fd::AsFd;
 use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::path::Path;
 
+use crate::error;
+
 impl crate::sys::fs::PathExt for Path {
     fn readable(&self) -> bool {
         nix::unistd::access(self, nix::unistd::AccessFlags::R_OK).is_ok()
@@ -56,3 +59,69 @@ fn try_get_file_type(path: &Path) -> Option<std::fs::FileType> {
 fn try_get_file_mode(path: &Path) -> Option<u32> {
     path.metadata().map(|metadata| metadata.mode()).ok()
 }
+
+/// Waits up to the given duration for input to become available for reading
+/// from the given file descriptor. Returns whether or not input is available;
+/// end-of-input counts as available, since a read will not block.
+pub(crate) fn poll_for_input<Fd: AsFd>(
+    fd: Fd,
+    timeout: std::time::Duration,
+) -> Result<bool, error::Error> {
+    let deadline = std::time::Instant::now().checked_add(timeout);
+
+    loop {
+        let remaining = deadline.map_or(std::time::Duration::MAX, |deadline| {
+            deadline.saturating_duration_since(std::time::Instant::now())
+        });
+
+        // poll() only accepts a limited timeout, so long waits may take multiple calls.
+        let remaining_ms = u16::try_from(remaining.as_millis()).unwrap_or(u16::MAX);
+
+        let mut poll_fds = [nix::poll::PollFd::new(
+            fd.as_fd(),
+            nix::poll::PollFlags::POLLIN,
+        )];
+
+        match nix::poll::poll(&mut poll_fds, remaining_ms) {
+            Ok(0) if remaining_ms < u16::MAX => return Ok(false),
+            Ok(0) | Err(nix::errno::Errno::EINTR) => (),
+            Ok(_) => return Ok(true),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Waits up to the given duration for input to become available for reading from
+/// the shell's standard input. Unlike `poll_for_input`, this takes into account any
+/// input already buffered by `std::io::stdin()`, which polling the underlying file
+/// descriptor can't see.
+pub(crate) fn poll_for_stdin_input(timeout: std::time::Duration) -> Result<bool, error::Error> {
+    use std::io::BufRead;
+    use std::os::fd::AsRawFd;
+
+    let stdin = std::io::stdin();
+    let fd = stdin.as_raw_fd();
+    let mut locked = stdin.lock();
+
+    // Temporarily make reads non-blocking, so filling the buffer only succeeds if input
+    // is already buffered or immediately available.
+    let flags = nix::fcntl::OFlag::from_bits_truncate(nix::fcntl::fcntl(
+        fd,
+        nix::fcntl::FcntlArg::F_GETFL,
+    )?);
+    nix::fcntl::fcntl(
+        fd,
+        nix::fcntl::FcntlArg::F_SETFL(flags | nix::fcntl::OFlag::O_NONBLOCK),
+    )?;
+    let fill_result = locked.fill_buf().map(|_| ());
+    nix::fcntl::fcntl(fd, nix::fcntl::FcntlArg::F_SETFL(flags))?;
+
+    match fill_result {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            drop(locked);
+            poll_for_input(std::io::stdin(), timeout)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
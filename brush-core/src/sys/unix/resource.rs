@@ -0,0 +1,79 @@
+use crate::error;
+use std::time::Duration;
+
+use nix::libc::rlim_t;
+use nix::sys::resource::{getrusage, Resource, UsageWho};
+
+/// Resource limits set within a subshell. Subshells run in the same process as their
+/// parent shell, so rather than changing the limits of the whole process, these are
+/// applied to the external commands the subshell runs.
+#[derive(Clone, Default)]
+pub(crate) struct ResourceLimits {
+    limits: Vec<(Resource, rlim_t, rlim_t)>,
+}
+
+impl ResourceLimits {
+    /// Returns the soft and hard limits set for the given resource, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource to look up.
+    pub(crate) fn get(&self, resource: Resource) -> Option<(rlim_t, rlim_t)> {
+        self.limits
+            .iter()
+            .find(|(r, _, _)| *r == resource)
+            .map(|(_, soft, hard)| (*soft, *hard))
+    }
+
+    /// Checks whether no limits have been set.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Sets the soft and hard limits for the given resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource` - The resource to set limits for.
+    /// * `soft` - The new soft limit.
+    /// * `hard` - The new hard limit.
+    pub(crate) fn set(&mut self, resource: Resource, soft: rlim_t, hard: rlim_t) {
+        self.limits.retain(|(r, _, _)| *r != resource);
+        self.limits.push((resource, soft, hard));
+    }
+
+    /// Applies the limits to the current process; meant to be called in a child process
+    /// just before it executes a command.
+    pub(crate) fn apply(&self) -> Result<(), std::io::Error> {
+        for (resource, soft, hard) in &self.limits {
+            nix::sys::resource::setrlimit(*resource, *soft, *hard)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the user and system CPU time consumed by the current process.
+pub(crate) fn get_self_user_and_system_time() -> Result<(Duration, Duration), error::Error> {
+    get_user_and_system_time(UsageWho::RUSAGE_SELF)
+}
+
+/// Returns the user and system CPU time consumed by the terminated (and waited-for)
+/// children of the current process.
+pub(crate) fn get_children_user_and_system_time() -> Result<(Duration, Duration), error::Error> {
+    get_user_and_system_time(UsageWho::RUSAGE_CHILDREN)
+}
+
+fn get_user_and_system_time(who: UsageWho) -> Result<(Duration, Duration), error::Error> {
+    let usage = getrusage(who)?;
+    Ok((
+        convert_timeval(&usage.user_time()),
+        convert_timeval(&usage.system_time()),
+    ))
+}
+
+fn convert_timeval(value: &nix::sys::time::TimeVal) -> Duration {
+    let secs = u64::try_from(value.tv_sec()).unwrap_or_default();
+    let micros = u64::try_from(value.tv_usec()).unwrap_or_default();
+    Duration::from_secs(secs) + Duration::from_micros(micros)
+}
@@ -1,5 +1,30 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
 use crate::{error, sys};
 
+#[allow(clippy::declare_interior_mutable_const)]
+const NOT_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Flags indicating receipt of trapped signals, indexed by signal number.
+static PENDING_TRAPPED_SIGNALS: [AtomicBool; 128] = [NOT_PENDING; 128];
+
+lazy_static::lazy_static! {
+    /// The dispositions signals had before they were first trapped or ignored; restored
+    /// when a signal's trap is reset.
+    static ref ORIGINAL_SIGNAL_ACTIONS:
+        Mutex<HashMap<nix::sys::signal::Signal, nix::sys::signal::SigAction>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Signals whose dispositions the shell itself depends on; traps for these signals
+/// don't affect their dispositions.
+const SHELL_MANAGED_SIGNALS: &[nix::sys::signal::Signal] = &[
+    nix::sys::signal::Signal::SIGCHLD,
+    nix::sys::signal::Signal::SIGTTOU,
+];
+
 pub(crate) fn continue_process(pid: sys::process::ProcessId) -> Result<(), error::Error> {
     #[allow(clippy::cast_possible_wrap)]
     nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), nix::sys::signal::SIGCONT)
@@ -44,6 +69,179 @@ pub(crate) fn mask_sigttou() -> Result<(), error::Error> {
     Ok(())
 }
 
+pub(crate) fn set_signal_trapped(signal: nix::sys::signal::Signal) -> Result<(), error::Error> {
+    if SHELL_MANAGED_SIGNALS.contains(&signal) {
+        return Ok(());
+    }
+
+    set_signal_handler(
+        signal,
+        nix::sys::signal::SigHandler::Handler(handle_trapped_signal),
+    )
+}
+
+pub(crate) fn set_signal_ignored(signal: nix::sys::signal::Signal) -> Result<(), error::Error> {
+    if SHELL_MANAGED_SIGNALS.contains(&signal) {
+        return Ok(());
+    }
+
+    set_signal_handler(signal, nix::sys::signal::SigHandler::SigIgn)
+}
+
+pub(crate) fn set_signal_default(signal: nix::sys::signal::Signal) -> Result<(), error::Error> {
+    let original_action = ORIGINAL_SIGNAL_ACTIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&signal);
+
+    if let Some(action) = original_action {
+        unsafe { nix::sys::signal::sigaction(signal, &action) }?;
+    }
+
+    Ok(())
+}
+
+/// The state of a signal before a subshell first changed its disposition.
+struct SavedSignalState {
+    action: nix::libc::sigaction,
+    original_action: Option<nix::sys::signal::SigAction>,
+    pending: bool,
+}
+
+/// Saves the state of the signals whose dispositions a subshell changes, and restores it when
+/// dropped. Subshells run in the same process as their parent shell, so this keeps the traps
+/// they set or reset from leaking into the parent.
+#[derive(Default)]
+pub(crate) struct SubshellSignalState {
+    saved: HashMap<nix::sys::signal::Signal, SavedSignalState>,
+}
+
+impl Clone for SubshellSignalState {
+    fn clone(&self) -> Self {
+        // N.B. Only the original is responsible for restoring the saved state.
+        Self::default()
+    }
+}
+
+impl SubshellSignalState {
+    /// Saves the state of the given signal, if it hasn't already been saved; to be called
+    /// before the subshell changes the signal's disposition.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal whose state should be saved.
+    pub(crate) fn save(&mut self, signal: nix::sys::signal::Signal) -> Result<(), error::Error> {
+        if SHELL_MANAGED_SIGNALS.contains(&signal) || self.saved.contains_key(&signal) {
+            return Ok(());
+        }
+
+        let mut action = std::mem::MaybeUninit::<nix::libc::sigaction>::uninit();
+        nix::errno::Errno::result(unsafe {
+            nix::libc::sigaction(
+                signal as nix::libc::c_int,
+                std::ptr::null(),
+                action.as_mut_ptr(),
+            )
+        })?;
+        let action = unsafe { action.assume_init() };
+
+        let original_action = ORIGINAL_SIGNAL_ACTIONS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&signal)
+            .copied();
+
+        // Set aside any receipt of the signal that the parent has yet to handle.
+        let pending = take_pending_trapped_signal(signal);
+
+        self.saved.insert(
+            signal,
+            SavedSignalState {
+                action,
+                original_action,
+                pending,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+impl Drop for SubshellSignalState {
+    fn drop(&mut self) {
+        let mut original_actions = ORIGINAL_SIGNAL_ACTIONS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        for (signal, saved) in self.saved.drain() {
+            // N.B. Errors can't be reported from here; restoring a disposition that was
+            // previously in effect isn't expected to fail.
+            let _ = unsafe {
+                nix::libc::sigaction(
+                    signal as nix::libc::c_int,
+                    &saved.action,
+                    std::ptr::null_mut(),
+                )
+            };
+
+            match saved.original_action {
+                Some(action) => {
+                    original_actions.insert(signal, action);
+                }
+                None => {
+                    original_actions.remove(&signal);
+                }
+            }
+
+            if let Some(pending) = usize::try_from(signal as i32)
+                .ok()
+                .and_then(|index| PENDING_TRAPPED_SIGNALS.get(index))
+            {
+                pending.store(saved.pending, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+pub(crate) fn take_pending_trapped_signal(signal: nix::sys::signal::Signal) -> bool {
+    usize::try_from(signal as i32)
+        .ok()
+        .and_then(|index| PENDING_TRAPPED_SIGNALS.get(index))
+        .is_some_and(|pending| pending.swap(false, Ordering::SeqCst))
+}
+
+extern "C" fn handle_trapped_signal(signal: nix::libc::c_int) {
+    // N.B. Only async-signal-safe operations may be performed here.
+    if let Some(pending) = usize::try_from(signal)
+        .ok()
+        .and_then(|index| PENDING_TRAPPED_SIGNALS.get(index))
+    {
+        pending.store(true, Ordering::SeqCst);
+    }
+}
+
+fn set_signal_handler(
+    signal: nix::sys::signal::Signal,
+    handler: nix::sys::signal::SigHandler,
+) -> Result<(), error::Error> {
+    let action = nix::sys::signal::SigAction::new(
+        handler,
+        nix::sys::signal::SaFlags::SA_RESTART,
+        nix::sys::signal::SigSet::empty(),
+    );
+    let prior_action = unsafe { nix::sys::signal::sigaction(signal, &action) }?;
+
+    // Remember the disposition the signal had before we first changed it, so it can
+    // be restored later.
+    ORIGINAL_SIGNAL_ACTIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(signal)
+        .or_insert(prior_action);
+
+    Ok(())
+}
+
 pub(crate) fn poll_for_stopped_children() -> Result<bool, error::Error> {
     let mut found_stopped = false;
 
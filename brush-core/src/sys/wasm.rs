@@ -2,6 +2,7 @@ pub(crate) use crate::sys::stubs::fs;
 pub(crate) use crate::sys::stubs::network;
 pub(crate) use crate::sys::stubs::pipes;
 pub(crate) use crate::sys::stubs::process;
+pub(crate) use crate::sys::stubs::resource;
 pub(crate) use crate::sys::stubs::signal;
 pub(crate) use crate::sys::stubs::terminal;
 pub(crate) use crate::sys::stubs::users;
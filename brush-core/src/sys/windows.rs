@@ -7,6 +7,7 @@ pub(crate) mod signal {
     pub(crate) use tokio::signal::ctrl_c as await_ctrl_c;
 }
 
+pub(crate) use crate::sys::stubs::resource;
 pub(crate) use crate::sys::stubs::terminal;
 pub(crate) use crate::sys::tokio_process as process;
 pub(crate) mod users;
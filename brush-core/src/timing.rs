@@ -0,0 +1,172 @@
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use crate::{error, sys};
+
+/// The format used to report timings when `TIMEFORMAT` is unset.
+pub(crate) const DEFAULT_TIME_FORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+/// The format used to report timings in POSIX mode (i.e., `time -p`).
+pub(crate) const POSIX_TIME_FORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+/// Measures the real, user, and system time elapsed while running a command.
+pub(crate) struct Stopwatch {
+    start: Instant,
+    user: Duration,
+    system: Duration,
+}
+
+impl Stopwatch {
+    /// Starts a new stopwatch.
+    pub fn start() -> Result<Self, error::Error> {
+        let (user, system) = get_user_and_system_time()?;
+
+        Ok(Self {
+            start: Instant::now(),
+            user,
+            system,
+        })
+    }
+
+    /// Returns the time elapsed since the stopwatch was started.
+    pub fn elapsed(&self) -> Result<Timings, error::Error> {
+        let real = self.start.elapsed();
+        let (user, system) = get_user_and_system_time()?;
+
+        Ok(Timings {
+            real,
+            user: user.saturating_sub(self.user),
+            system: system.saturating_sub(self.system),
+        })
+    }
+}
+
+/// Returns the user and system time consumed by this process and its waited-for children.
+fn get_user_and_system_time() -> Result<(Duration, Duration), error::Error> {
+    let times = ProcessTimes::get()?;
+
+    Ok((
+        times.self_user + times.children_user,
+        times.self_system + times.children_system,
+    ))
+}
+
+/// CPU time consumed by the shell and by its terminated (and waited-for) children.
+pub(crate) struct ProcessTimes {
+    /// User time consumed by the shell.
+    pub self_user: Duration,
+    /// System time consumed by the shell.
+    pub self_system: Duration,
+    /// User time consumed by the shell's children.
+    pub children_user: Duration,
+    /// System time consumed by the shell's children.
+    pub children_system: Duration,
+}
+
+impl ProcessTimes {
+    /// Retrieves the CPU time consumed so far.
+    pub fn get() -> Result<Self, error::Error> {
+        let (self_user, self_system) = sys::resource::get_self_user_and_system_time()?;
+        let (children_user, children_system) = sys::resource::get_children_user_and_system_time()?;
+
+        Ok(Self {
+            self_user,
+            self_system,
+            children_user,
+            children_system,
+        })
+    }
+}
+
+/// Elapsed real, user, and system time.
+pub(crate) struct Timings {
+    real: Duration,
+    user: Duration,
+    system: Duration,
+}
+
+impl Timings {
+    /// Formats the timings as described by the given `TIMEFORMAT`-style format string.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format string.
+    pub fn format(&self, format: &str) -> Result<String, error::Error> {
+        let mut output = String::new();
+        let mut chars = format.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                output.push(c);
+                continue;
+            }
+
+            match chars.peek() {
+                Some('%') => {
+                    chars.next();
+                    output.push('%');
+                }
+                Some('P') => {
+                    chars.next();
+                    write!(output, "{}", format_cpu_percentage(self))?;
+                }
+                Some(c) if c.is_ascii_digit() || matches!(*c, 'l' | 'R' | 'E' | 'U' | 'S') => {
+                    // Precision defaults to 3, and may not exceed 3.
+                    let mut precision = 3;
+                    if let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        chars.next();
+                        precision = digit.min(3);
+                    }
+
+                    let long = chars.next_if_eq(&'l').is_some();
+
+                    let duration = match chars.next() {
+                        Some('R' | 'E') => self.real,
+                        Some('U') => self.user,
+                        Some('S') => self.system,
+                        Some(c) => return Err(error::Error::InvalidTimeFormatCharacter(c)),
+                        None => return Err(error::Error::InvalidTimeFormatCharacter('\0')),
+                    };
+
+                    write!(output, "{}", format_duration(&duration, precision, long))?;
+                }
+                _ => output.push('%'),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Formats a duration in seconds with the given number of fractional digits (at most 3);
+/// if `long` is set, the minutes are split out, as in `1m2.345s`.
+pub(crate) fn format_duration(duration: &Duration, precision: u32, long: bool) -> String {
+    // N.B. The fractional part is truncated, not rounded.
+    let fraction = if precision > 0 {
+        std::format!(
+            ".{:0width$}",
+            duration.subsec_millis() / 10u32.pow(3 - precision),
+            width = precision as usize
+        )
+    } else {
+        String::new()
+    };
+
+    let secs = duration.as_secs();
+    if long {
+        std::format!("{}m{}{fraction}s", secs / 60, secs % 60)
+    } else {
+        std::format!("{secs}{fraction}")
+    }
+}
+
+fn format_cpu_percentage(timings: &Timings) -> String {
+    let real = timings.real.as_micros();
+    let cpu = (timings.user + timings.system).as_micros();
+
+    // Compute the percentage in hundredths of a percent. N.B. This isn't capped at 100%,
+    // since commands running in parallel can use more CPU time than real time elapses.
+    let hundredths = if real == 0 { 0 } else { cpu * 10000 / real };
+
+    std::format!("{}.{:02}", hundredths / 100, hundredths % 100)
+}
@@ -4,6 +4,7 @@ use std::{collections::HashMap, fmt::Display};
 use itertools::Itertools as _;
 
 use crate::error;
+use crate::options::RuntimeOptions;
 
 /// Type of signal that can be trapped in the shell.
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
@@ -17,6 +18,8 @@ pub enum TrapSignal {
     Err,
     /// The `EXIT` trap.
     Exit,
+    /// The `RETURN` trap.
+    Return,
 }
 
 impl Display for TrapSignal {
@@ -28,7 +31,12 @@ impl Display for TrapSignal {
 impl TrapSignal {
     /// Returns all possible values of [`TrapSignal`].
     pub fn iterator() -> impl Iterator<Item = TrapSignal> {
-        const SIGNALS: &[TrapSignal] = &[TrapSignal::Debug, TrapSignal::Err, TrapSignal::Exit];
+        const SIGNALS: &[TrapSignal] = &[
+            TrapSignal::Debug,
+            TrapSignal::Err,
+            TrapSignal::Exit,
+            TrapSignal::Return,
+        ];
         let iter = SIGNALS.iter().copied();
 
         #[cfg(unix)]
@@ -48,6 +56,7 @@ impl TrapSignal {
             TrapSignal::Debug => "DEBUG",
             TrapSignal::Err => "ERR",
             TrapSignal::Exit => "EXIT",
+            TrapSignal::Return => "RETURN",
         }
     }
 }
@@ -86,7 +95,7 @@ impl FromStr for TrapSignal {
 impl TryFrom<i32> for TrapSignal {
     type Error = error::Error;
     fn try_from(value: i32) -> Result<Self, Self::Error> {
-        // NOTE: DEBUG and ERR are real-time signals, defined based on NSIG or SIGRTMAX (is not
+        // NOTE: DEBUG, ERR and RETURN are real-time signals, defined based on NSIG or SIGRTMAX (is not
         // available on bsd-like systems),
         // and don't have persistent numbers across platforms, so we skip them here.
         Ok(match value {
@@ -113,6 +122,7 @@ impl TryFrom<&str> for TrapSignal {
             "DEBUG" => TrapSignal::Debug,
             "ERR" => TrapSignal::Err,
             "EXIT" => TrapSignal::Exit,
+            "RETURN" => TrapSignal::Return,
 
             #[cfg(unix)]
             _ => {
@@ -153,25 +163,88 @@ pub struct TrapHandlerConfig {
     pub handlers: HashMap<TrapSignal, String>,
     /// Current depth of the handler stack.
     pub handler_depth: i32,
+    /// In a subshell, the saved state of signals whose dispositions the subshell changed,
+    /// which is restored once the subshell is done.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    subshell_signals: Option<crate::sys::signal::SubshellSignalState>,
 }
 
 impl TrapHandlerConfig {
-    /// Registers a handler for a trap signal.
+    /// Registers a handler for a trap signal. For system signals, this also updates the
+    /// shell's disposition for the signal: an empty handler causes the signal to be ignored
+    /// (by the shell and by any commands it runs); otherwise, the signal is caught so the
+    /// handler can be run.
     ///
     /// # Arguments
     ///
     /// * `signal_type` - The type of signal to register a handler for.
     /// * `command` - The command to execute when the signal is trapped.
-    pub fn register_handler(&mut self, signal_type: TrapSignal, command: String) {
+    pub fn register_handler(
+        &mut self,
+        signal_type: TrapSignal,
+        command: String,
+    ) -> Result<(), error::Error> {
+        #[cfg(unix)]
+        if let TrapSignal::Signal(signal) = signal_type {
+            if let Some(subshell_signals) = &mut self.subshell_signals {
+                subshell_signals.save(signal)?;
+            }
+
+            if command.is_empty() {
+                crate::sys::signal::set_signal_ignored(signal)?;
+            } else {
+                crate::sys::signal::set_signal_trapped(signal)?;
+            }
+        }
+
         let _ = self.handlers.insert(signal_type, command);
+
+        Ok(())
     }
 
-    /// Removes handlers for a trap signal.
+    /// Removes handlers for a trap signal. For system signals, this also restores the
+    /// signal's default disposition.
     ///
     /// # Arguments
     ///
     /// * `signal_type` - The type of signal to remove handlers for.
-    pub fn remove_handlers(&mut self, signal_type: TrapSignal) {
+    pub fn remove_handlers(&mut self, signal_type: TrapSignal) -> Result<(), error::Error> {
+        #[cfg(unix)]
+        if let TrapSignal::Signal(signal) = signal_type {
+            if let Some(subshell_signals) = &mut self.subshell_signals {
+                subshell_signals.save(signal)?;
+            }
+
+            crate::sys::signal::set_signal_default(signal)?;
+        }
+
         self.handlers.remove(&signal_type);
+
+        Ok(())
+    }
+
+    /// Resets the handlers that a subshell doesn't inherit from its parent: `EXIT` and
+    /// signal handlers are dropped, with the exception of ignored signals, which remain
+    /// ignored. The `ERR` handler is only inherited with errtrace (`set -E`), and the `DEBUG`
+    /// and `RETURN` handlers are only inherited with functrace (`set -T`). Any signal
+    /// dispositions the subshell goes on to change are restored once it's dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The runtime options of the subshell.
+    pub(crate) fn reset_for_subshell(&mut self, options: &RuntimeOptions) {
+        #[allow(unused_variables)] // on not unix platforms
+        self.handlers
+            .retain(|signal_type, command| match signal_type {
+                TrapSignal::Debug | TrapSignal::Return => {
+                    options.shell_functions_inherit_debug_and_return_traps
+                }
+                TrapSignal::Err => options.shell_functions_inherit_err_trap,
+                TrapSignal::Exit => false,
+                #[cfg(unix)]
+                TrapSignal::Signal(_) => command.is_empty(),
+            });
+
+        self.subshell_signals = Some(crate::sys::signal::SubshellSignalState::default());
     }
 }
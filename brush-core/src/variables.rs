@@ -2,6 +2,7 @@ use rand::Rng;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::{Display, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use crate::{error, escape};
 
@@ -70,6 +71,14 @@ impl ShellVariable {
         &self.value
     }
 
+    /// Returns a mutable reference to the variable's dynamic value, if it has one.
+    pub(crate) fn dynamic_value_mut(&mut self) -> Option<&mut DynamicValue> {
+        match &mut self.value {
+            ShellValue::Dynamic(dynamic_value) => Some(dynamic_value),
+            _ => None,
+        }
+    }
+
     /// Returns whether or not the variable is exported to child processes.
     pub fn is_exported(&self) -> bool {
         self.exported
@@ -170,6 +179,19 @@ impl ShellVariable {
         self.treat_as_nameref = false;
     }
 
+    /// If the variable is a name reference that currently refers to another variable,
+    /// returns the name of the referenced variable.
+    pub fn nameref_target(&self) -> Option<&str> {
+        if !self.treat_as_nameref {
+            return None;
+        }
+
+        match &self.value {
+            ShellValue::String(s) if !s.is_empty() => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
     /// Converts the variable to an indexed array.
     pub fn convert_to_indexed_array(&mut self) -> Result<(), error::Error> {
         match self.value() {
@@ -280,7 +302,8 @@ impl ShellVariable {
                     }
                 },
                 ShellValue::Unset(_) => unreachable!("covered in conversion above"),
-                ShellValue::Random => Ok(()),
+                // Drop appends to dynamic values.
+                ShellValue::Dynamic(_) => Ok(()),
             }
         } else {
             match (&self.value, value) {
@@ -304,7 +327,7 @@ impl ShellVariable {
                         ShellValueUnsetType::IndexedArray | ShellValueUnsetType::Untyped,
                     )
                     | ShellValue::String(_)
-                    | ShellValue::Random,
+                    | ShellValue::Dynamic(_),
                     ShellValueLiteral::Array(literal_values),
                 ) => {
                     self.value = ShellValue::indexed_array_from_literals(literal_values)?;
@@ -322,8 +345,11 @@ impl ShellVariable {
                     Ok(())
                 }
 
-                // Drop other updates to random values.
-                (ShellValue::Random, _) => Ok(()),
+                // Let dynamic values decide how to handle scalar assignments.
+                (ShellValue::Dynamic(dynamic_value), ShellValueLiteral::Scalar(s)) => {
+                    dynamic_value.assign(s.as_str());
+                    Ok(())
+                }
 
                 // Assign a scalar value to a scalar or unset (and untyped) variable.
                 (ShellValue::String(_) | ShellValue::Unset(_), ShellValueLiteral::Scalar(s)) => {
@@ -429,7 +455,7 @@ impl ShellVariable {
                     Ok(false)
                 }
             },
-            ShellValue::String(_) | ShellValue::Random => Err(error::Error::NotArray),
+            ShellValue::String(_) | ShellValue::Dynamic(_) => Err(error::Error::NotArray),
             ShellValue::AssociativeArray(values) => Ok(values.remove(index).is_some()),
             ShellValue::IndexedArray(values) => {
                 let key = index.parse::<u64>().unwrap_or(0);
@@ -492,8 +518,218 @@ pub enum ShellValue {
     AssociativeArray(BTreeMap<String, String>),
     /// An indexed array.
     IndexedArray(BTreeMap<u64, String>),
-    /// A special value that yields a different random number each time its read.
-    Random,
+    /// A special value that is computed each time it's read.
+    Dynamic(DynamicValue),
+}
+
+/// A special value that is computed each time it's read, with its own semantics
+/// for assignment.
+#[derive(Clone, Debug)]
+pub enum DynamicValue {
+    /// Yields a pseudo-random integer between 0 and 32767; assigning a value seeds
+    /// the generator (`RANDOM`).
+    Random(RandomNumberGenerator),
+    /// Yields a 32-bit random number drawn from the system's entropy source; assignments
+    /// are ignored (`SRANDOM`).
+    SecureRandom,
+    /// Yields the number of seconds elapsed since counting started, offset by the
+    /// last value assigned (`SECONDS`).
+    Seconds {
+        /// The time at which counting started.
+        start: std::time::Instant,
+        /// The number of seconds to add to the elapsed time.
+        offset: i64,
+    },
+    /// Yields the number of seconds since the Unix epoch; assignments are ignored
+    /// (`EPOCHSECONDS`).
+    EpochSeconds,
+    /// Yields the number of seconds since the Unix epoch, with microsecond granularity;
+    /// assignments are ignored (`EPOCHREALTIME`).
+    EpochRealTime,
+    /// Yields the process ID of the current shell; assignments are ignored (`BASHPID`).
+    /// Subshells that run in-process (without forking) have no process of their own, so
+    /// they're assigned an ID above the range used for real process IDs.
+    ProcessId(Option<u32>),
+    /// Yields the history number of the command being executed, as last reported by
+    /// the owner of the command history; assignments are ignored (`HISTCMD`).
+    HistoryNumber(usize),
+    /// Yields the number of the line being executed, as last reported by the shell;
+    /// assignments are ignored (`LINENO`).
+    LineNumber(u32),
+}
+
+/// First ID handed out to in-process subshells; lies above the largest process ID
+/// any supported kernel assigns, so it can never name a real process.
+const FIRST_SUBSHELL_ID: u32 = 4_194_305;
+
+/// Next ID to be handed out to an in-process subshell.
+static NEXT_SUBSHELL_ID: AtomicU32 = AtomicU32::new(FIRST_SUBSHELL_ID);
+
+impl DynamicValue {
+    /// Returns a new value that counts seconds starting from now.
+    pub fn seconds() -> Self {
+        Self::Seconds {
+            start: std::time::Instant::now(),
+            offset: 0,
+        }
+    }
+
+    /// Computes the current value.
+    pub fn get(&self) -> String {
+        match self {
+            DynamicValue::Random(generator) => generator.next_value().to_string(),
+            DynamicValue::SecureRandom => rand::rngs::OsRng.gen::<u32>().to_string(),
+            DynamicValue::Seconds { start, offset } => {
+                let elapsed = i64::try_from(start.elapsed().as_secs()).unwrap_or(i64::MAX);
+                offset.saturating_add(elapsed).to_string()
+            }
+            DynamicValue::EpochSeconds => get_time_since_epoch().as_secs().to_string(),
+            DynamicValue::EpochRealTime => {
+                let since_epoch = get_time_since_epoch();
+                std::format!(
+                    "{}.{:06}",
+                    since_epoch.as_secs(),
+                    since_epoch.subsec_micros()
+                )
+            }
+            DynamicValue::ProcessId(subshell_id) => {
+                subshell_id.unwrap_or_else(std::process::id).to_string()
+            }
+            DynamicValue::HistoryNumber(number) => number.to_string(),
+            DynamicValue::LineNumber(number) => number.to_string(),
+        }
+    }
+
+    /// Updates the value for use in a newly created subshell: the random number
+    /// generator is reseeded so the subshell doesn't replay its parent's sequence,
+    /// and the subshell is given its own process ID.
+    pub(crate) fn reset_for_subshell(&mut self) {
+        match self {
+            DynamicValue::Random(generator) => {
+                generator.seed(i64::from(rand::thread_rng().gen::<u32>()));
+            }
+            DynamicValue::ProcessId(subshell_id) => {
+                *subshell_id = Some(NEXT_SUBSHELL_ID.fetch_add(1, Ordering::Relaxed));
+            }
+            DynamicValue::SecureRandom
+            | DynamicValue::Seconds { .. }
+            | DynamicValue::EpochSeconds
+            | DynamicValue::EpochRealTime
+            | DynamicValue::HistoryNumber(_)
+            | DynamicValue::LineNumber(_) => (),
+        }
+    }
+
+    /// Applies an assignment of the given value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value being assigned.
+    pub fn assign(&mut self, value: &str) {
+        match self {
+            DynamicValue::Random(generator) => {
+                if let Ok(seed) = value.trim().parse::<i64>() {
+                    generator.seed(seed);
+                }
+            }
+            DynamicValue::Seconds { start, offset } => {
+                *start = std::time::Instant::now();
+                *offset = value.trim().parse().unwrap_or(0);
+            }
+            DynamicValue::SecureRandom
+            | DynamicValue::EpochSeconds
+            | DynamicValue::EpochRealTime
+            | DynamicValue::ProcessId(_)
+            | DynamicValue::HistoryNumber(_)
+            | DynamicValue::LineNumber(_) => (),
+        }
+    }
+}
+
+/// Seedable pseudo-random number generator backing `RANDOM`. Uses the same algorithm
+/// as bash, so that seeded sequences match.
+#[derive(Debug)]
+pub struct RandomNumberGenerator {
+    /// Current generator state.
+    state: AtomicU32,
+    /// Most recently generated value; consecutive values are never equal.
+    last_value: AtomicU32,
+}
+
+impl Default for RandomNumberGenerator {
+    fn default() -> Self {
+        Self {
+            state: AtomicU32::new(rand::thread_rng().gen()),
+            last_value: AtomicU32::new(0),
+        }
+    }
+}
+
+impl Clone for RandomNumberGenerator {
+    fn clone(&self) -> Self {
+        Self {
+            state: AtomicU32::new(self.state.load(Ordering::Relaxed)),
+            last_value: AtomicU32::new(self.last_value.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl RandomNumberGenerator {
+    const MAX_VALUE: u32 = 0x7fff;
+
+    /// Reseeds the generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The new seed; only its low 32 bits are used.
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn seed(&self, seed: i64) {
+        self.state.store(seed as u32, Ordering::Relaxed);
+        self.last_value.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the next value in the sequence.
+    pub fn next_value(&self) -> u32 {
+        let mut state = self.state.load(Ordering::Relaxed);
+        let last_value = self.last_value.load(Ordering::Relaxed);
+
+        let value = loop {
+            state = Self::advance(state);
+            let value = ((state >> 16) ^ (state & 0xffff)) & Self::MAX_VALUE;
+            if value != last_value {
+                break value;
+            }
+        };
+
+        self.state.store(state, Ordering::Relaxed);
+        self.last_value.store(value, Ordering::Relaxed);
+
+        value
+    }
+
+    /// Advances the given state using the Park-Miller "minimal standard" generator.
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn advance(state: u32) -> u32 {
+        let state = if state == 0 { 123_459_876 } else { state };
+
+        let high = i64::from(state / 127_773);
+        let low = i64::from(state % 127_773);
+
+        let mut next = 16807 * low - 2836 * high;
+        if next < 0 {
+            next += 0x7fff_ffff;
+        }
+
+        next as u32
+    }
+}
+
+fn get_time_since_epoch() -> std::time::Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
 }
 
 /// The type of an unset shell value.
@@ -731,7 +967,9 @@ impl ShellValue {
                 result.push(')');
                 Ok(result.into())
             }
-            ShellValue::Random => Ok(std::format!("\"{}\"", get_random_str()).into()),
+            ShellValue::Dynamic(dynamic_value) => {
+                Ok(std::format!("\"{}\"", dynamic_value.get()).into())
+            }
         }
     }
 
@@ -758,7 +996,13 @@ impl ShellValue {
                 let key = index.parse::<u64>().unwrap_or(0);
                 Ok(values.get(&key).map(|s| Cow::Borrowed(s.as_str())))
             }
-            ShellValue::Random => Ok(Some(Cow::Owned(get_random_str()))),
+            ShellValue::Dynamic(dynamic_value) => {
+                if index.parse::<u64>().unwrap_or(0) == 0 {
+                    Ok(Some(Cow::Owned(dynamic_value.get())))
+                } else {
+                    Ok(None)
+                }
+            }
         }
     }
 
@@ -766,7 +1010,7 @@ impl ShellValue {
     pub fn get_element_keys(&self) -> Vec<String> {
         match self {
             ShellValue::Unset(_) => vec![],
-            ShellValue::String(_) | ShellValue::Random => vec!["0".to_owned()],
+            ShellValue::String(_) | ShellValue::Dynamic(_) => vec!["0".to_owned()],
             ShellValue::AssociativeArray(array) => array.keys().map(|k| k.to_owned()).collect(),
             ShellValue::IndexedArray(array) => array.keys().map(|k| k.to_string()).collect(),
         }
@@ -779,7 +1023,7 @@ impl ShellValue {
             ShellValue::String(s) => vec![s.to_owned()],
             ShellValue::AssociativeArray(array) => array.values().map(|v| v.to_owned()).collect(),
             ShellValue::IndexedArray(array) => array.values().map(|v| v.to_owned()).collect(),
-            ShellValue::Random => vec![get_random_str()],
+            ShellValue::Dynamic(dynamic_value) => vec![dynamic_value.get()],
         }
     }
 
@@ -794,7 +1038,7 @@ impl ShellValue {
             ShellValue::IndexedArray(values) => values
                 .get(&0)
                 .map_or_else(|| Cow::Borrowed(""), |s| Cow::Borrowed(s.as_str())),
-            ShellValue::Random => Cow::Owned(get_random_str()),
+            ShellValue::Dynamic(dynamic_value) => Cow::Owned(dynamic_value.get()),
         }
     }
 
@@ -818,7 +1062,9 @@ impl ShellValue {
                     self.format(FormatStyle::DeclarePrint).unwrap().into_owned()
                 }
             }
-            ShellValue::Random => quote_str_for_assignment(get_random_str().as_str()),
+            ShellValue::Dynamic(dynamic_value) => {
+                quote_str_for_assignment(dynamic_value.get().as_str())
+            }
         }
     }
 }
@@ -841,12 +1087,6 @@ impl From<String> for ShellValue {
     }
 }
 
-pub(crate) fn get_random_str() -> String {
-    let mut rng = rand::thread_rng();
-    let value = rng.gen_range(0..32768);
-    value.to_string()
-}
-
 pub(crate) fn quote_str_for_assignment(s: &str) -> String {
     let mut result = String::new();
 
@@ -49,6 +49,11 @@ pub trait InteractiveShell {
     /// Update history, if relevant.
     fn update_history(&mut self) -> Result<(), ShellError>;
 
+    /// Returns the number of entries in the command history, if history is kept.
+    fn history_count(&self) -> Option<usize> {
+        None
+    }
+
     /// Runs the interactive shell loop, reading commands from standard input and writing
     /// results to standard output and standard error. Continues until the shell
     /// normally exits or until a fatal error occurs.
@@ -133,8 +138,14 @@ pub trait InteractiveShell {
 
             match self.read_line(prompt)? {
                 ReadResult::Input(read_result) => {
+                    let history_count = self.history_count();
                     let mut shell_mut = self.shell_mut();
 
+                    // The line just read is the latest entry in the history.
+                    if let Some(history_count) = history_count {
+                        shell_mut.as_mut().set_history_number(history_count);
+                    }
+
                     if shell_mut.as_mut().user_tried_exiting > 0 && !read_result.is_empty() {
                         shell_mut.as_mut().user_tried_exiting -= 1;
                     }
@@ -24,9 +24,12 @@ impl ReedlineShell {
     pub async fn new(options: &crate::Options) -> Result<ReedlineShell, ShellError> {
         // Set up shell first. Its initialization may influence how the
         // editor needs to operate.
-        let shell = brush_core::Shell::new(&options.shell).await?;
+        let mut shell = brush_core::Shell::new(&options.shell).await?;
         let history_file_path = shell.get_history_file_path();
 
+        // Make the line editor available to builtins like `read -e`.
+        shell.line_editor = Some(read_line_with_editor);
+
         let shell_ref = Arc::new(Mutex::new(shell));
 
         // Create helper objects that implement reedline traits; each will
@@ -133,6 +136,34 @@ impl InteractiveShell for ReedlineShell {
         // N.B. With our current usage, reedline auto-updates the history file.
         Ok(())
     }
+
+    /// Returns the number of entries in the command history.
+    fn history_count(&self) -> Option<usize> {
+        let count = self.reedline.history().count_all().ok()?;
+        usize::try_from(count).ok()
+    }
+}
+
+/// Reads a single line of input using a standalone editor, pre-filled with the given
+/// initial text. Used to implement `read -e`.
+fn read_line_with_editor(
+    prompt: &str,
+    initial_text: &str,
+) -> Result<Option<String>, brush_core::Error> {
+    let mut editor = reedline::Reedline::create();
+    editor.run_edit_commands(&[reedline::EditCommand::InsertString(initial_text.to_owned())]);
+
+    let prompt = InteractivePrompt {
+        prompt: prompt.to_owned(),
+        alt_side_prompt: String::new(),
+        continuation_prompt: String::new(),
+    };
+
+    match editor.read_line(&prompt)? {
+        reedline::Signal::Success(s) => Ok(Some(s)),
+        reedline::Signal::CtrlC => Err(brush_core::Error::Interrupted),
+        reedline::Signal::CtrlD => Ok(None),
+    }
 }
 
 fn compose_key_bindings(completion_menu_name: &str) -> reedline::Keybindings {
@@ -102,15 +102,24 @@ impl Display for AndOr {
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub struct Pipeline {
+    /// Indicates whether the pipeline's execution should be timed with reported
+    /// timings in output.
+    pub timed: Option<PipelineTimed>,
     /// Indicates whether the result of the overall pipeline should be the logical
     /// negation of the result of the pipeline.
     pub bang: bool,
     /// The sequence of commands in the pipeline.
     pub seq: Vec<Command>,
+    /// The position in its source at which the pipeline starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for Pipeline {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(timed) = &self.timed {
+            write!(f, "{} ", timed)?;
+        }
+
         if self.bang {
             write!(f, "!")?;
         }
@@ -125,6 +134,27 @@ impl Display for Pipeline {
     }
 }
 
+/// Indicates how a pipeline's execution should be timed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub enum PipelineTimed {
+    /// The pipeline should be timed, with the timings reported in the format
+    /// given by `TIMEFORMAT`.
+    Timed,
+    /// The pipeline should be timed, with the timings reported in the POSIX format.
+    TimedWithPosixOutput,
+}
+
+impl Display for PipelineTimed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineTimed::Timed => write!(f, "time"),
+            PipelineTimed::TimedWithPosixOutput => write!(f, "time -p"),
+        }
+    }
+}
+
 /// Represents a shell command.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -141,6 +171,18 @@ pub enum Command {
     ExtendedTest(ExtendedTestExpr),
 }
 
+impl Command {
+    /// Returns the position in its source at which the command starts, if known.
+    pub fn location(&self) -> Option<&tokenizer::SourcePosition> {
+        match self {
+            Command::Simple(simple_command) => Some(&simple_command.location),
+            Command::Compound(compound_command, _) => Some(compound_command.location()),
+            Command::Function(function_definition) => Some(&function_definition.location),
+            Command::ExtendedTest(_) => None,
+        }
+    }
+}
+
 impl Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -175,6 +217,8 @@ pub enum CompoundCommand {
     Subshell(SubshellCommand),
     /// A for clause, which loops over a set of values.
     ForClause(ForClauseCommand),
+    /// A select clause, which repeatedly prompts the user to select one of a set of values.
+    SelectClause(SelectClauseCommand),
     /// A case clause, which selects a command based on a value and a set of
     /// pattern-based filters.
     CaseClause(CaseClauseCommand),
@@ -184,6 +228,32 @@ pub enum CompoundCommand {
     WhileClause(WhileOrUntilClauseCommand),
     /// An until clause, which loops until a condition is met.
     UntilClause(WhileOrUntilClauseCommand),
+    /// A coprocess, which runs a command asynchronously with its input and output
+    /// connected to the shell through pipes.
+    Coprocess(CoprocessCommand),
+}
+
+impl CompoundCommand {
+    /// Returns the position in its source at which the command starts.
+    pub fn location(&self) -> &tokenizer::SourcePosition {
+        match self {
+            CompoundCommand::Arithmetic(arithmetic_command) => &arithmetic_command.location,
+            CompoundCommand::ArithmeticForClause(arithmetic_for_clause_command) => {
+                &arithmetic_for_clause_command.location
+            }
+            CompoundCommand::BraceGroup(brace_group_command) => &brace_group_command.1,
+            CompoundCommand::Subshell(subshell_command) => &subshell_command.1,
+            CompoundCommand::ForClause(for_clause_command) => &for_clause_command.location,
+            CompoundCommand::SelectClause(select_clause_command) => &select_clause_command.location,
+            CompoundCommand::CaseClause(case_clause_command) => &case_clause_command.location,
+            CompoundCommand::IfClause(if_clause_command) => &if_clause_command.location,
+            CompoundCommand::WhileClause(while_or_until_clause_command)
+            | CompoundCommand::UntilClause(while_or_until_clause_command) => {
+                &while_or_until_clause_command.2
+            }
+            CompoundCommand::Coprocess(coprocess_command) => &coprocess_command.location,
+        }
+    }
 }
 
 impl Display for CompoundCommand {
@@ -198,6 +268,9 @@ impl Display for CompoundCommand {
             }
             CompoundCommand::Subshell(subshell_command) => write!(f, "{}", subshell_command),
             CompoundCommand::ForClause(for_clause_command) => write!(f, "{}", for_clause_command),
+            CompoundCommand::SelectClause(select_clause_command) => {
+                write!(f, "{}", select_clause_command)
+            }
             CompoundCommand::CaseClause(case_clause_command) => {
                 write!(f, "{}", case_clause_command)
             }
@@ -208,6 +281,7 @@ impl Display for CompoundCommand {
             CompoundCommand::UntilClause(while_or_until_clause_command) => {
                 write!(f, "until {}", while_or_until_clause_command)
             }
+            CompoundCommand::Coprocess(coprocess_command) => write!(f, "{}", coprocess_command),
         }
     }
 }
@@ -219,6 +293,8 @@ impl Display for CompoundCommand {
 pub struct ArithmeticCommand {
     /// The raw, unparsed and unexpanded arithmetic expression.
     pub expr: UnexpandedArithmeticExpr,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for ArithmeticCommand {
@@ -227,11 +303,12 @@ impl Display for ArithmeticCommand {
     }
 }
 
-/// A subshell, which executes commands in a subshell.
+/// A subshell, which executes commands in a subshell. Also holds the position in its
+/// source at which the command starts.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct SubshellCommand(pub CompoundList);
+pub struct SubshellCommand(pub CompoundList, pub tokenizer::SourcePosition);
 
 impl Display for SubshellCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -252,6 +329,8 @@ pub struct ForClauseCommand {
     pub values: Option<Vec<Word>>,
     /// The command to run for each iteration of the loop.
     pub body: DoGroupCommand,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for ForClauseCommand {
@@ -274,6 +353,64 @@ impl Display for ForClauseCommand {
     }
 }
 
+/// A select clause, which repeatedly prompts the user to select one of a set of values.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct SelectClauseCommand {
+    /// The name of the variable that receives the selected value.
+    pub variable_name: String,
+    /// The values to select from; if not provided, the positional parameters are used.
+    pub values: Option<Vec<Word>>,
+    /// The command to run for each selection.
+    pub body: DoGroupCommand,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
+}
+
+impl Display for SelectClauseCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "select {}", self.variable_name)?;
+
+        if let Some(values) = &self.values {
+            write!(f, " in")?;
+            for value in values {
+                write!(f, " {}", value)?;
+            }
+        }
+
+        writeln!(f, ";")?;
+
+        write!(f, "{}", self.body)
+    }
+}
+
+/// A coprocess, which runs a command asynchronously with its input and output
+/// connected to the shell through pipes.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub struct CoprocessCommand {
+    /// The name of the coprocess, if one was given.
+    pub name: Option<String>,
+    /// The command to run as the coprocess.
+    pub body: Box<Command>,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
+}
+
+impl Display for CoprocessCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "coproc ")?;
+
+        if let Some(name) = &self.name {
+            write!(f, "{} ", name)?;
+        }
+
+        write!(f, "{}", self.body)
+    }
+}
+
 /// An arithmetic for clause, which loops until an arithmetic condition is reached.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -287,6 +424,8 @@ pub struct ArithmeticForClauseCommand {
     pub updater: Option<UnexpandedArithmeticExpr>,
     /// The command to run for each iteration of the loop.
     pub body: DoGroupCommand,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for ArithmeticForClauseCommand {
@@ -325,6 +464,8 @@ pub struct CaseClauseCommand {
     pub value: Word,
     /// The individual case branches.
     pub cases: Vec<CaseItem>,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for CaseClauseCommand {
@@ -391,6 +532,8 @@ pub struct IfClauseCommand {
     pub then: CompoundList,
     /// Optionally, `else` clauses that will be evaluated if the condition is false.
     pub elses: Option<Vec<ElseClause>>,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for IfClauseCommand {
@@ -499,11 +642,16 @@ impl Display for CaseItemPostAction {
     }
 }
 
-/// A while or until clause, whose looping is controlled by a condition.
+/// A while or until clause, whose looping is controlled by a condition. Also holds the
+/// position in its source at which the command starts.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct WhileOrUntilClauseCommand(pub CompoundList, pub DoGroupCommand);
+pub struct WhileOrUntilClauseCommand(
+    pub CompoundList,
+    pub DoGroupCommand,
+    pub tokenizer::SourcePosition,
+);
 
 impl Display for WhileOrUntilClauseCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -522,6 +670,8 @@ pub struct FunctionDefinition {
     pub body: FunctionBody,
     /// The source of the function definition.
     pub source: String,
+    /// The position in its source at which the function definition starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for FunctionDefinition {
@@ -549,11 +699,12 @@ impl Display for FunctionBody {
     }
 }
 
-/// A brace group, which groups commands together.
+/// A brace group, which groups commands together. Also holds the position in its
+/// source at which the command starts.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
-pub struct BraceGroupCommand(pub CompoundList);
+pub struct BraceGroupCommand(pub CompoundList, pub tokenizer::SourcePosition);
 
 impl Display for BraceGroupCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -592,6 +743,8 @@ pub struct SimpleCommand {
     pub word_or_name: Option<Word>,
     /// Optionally, a suffix to the command.
     pub suffix: Option<CommandSuffix>,
+    /// The position in its source at which the command starts.
+    pub location: tokenizer::SourcePosition,
 }
 
 impl Display for SimpleCommand {
@@ -812,11 +965,11 @@ impl Display for RedirectList {
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub enum IoRedirect {
     /// Redirection to a file.
-    File(Option<u32>, IoFileRedirectKind, IoFileRedirectTarget),
+    File(Option<IoFd>, IoFileRedirectKind, IoFileRedirectTarget),
     /// Redirection from a here-document.
-    HereDocument(Option<u32>, IoHereDocument),
+    HereDocument(Option<IoFd>, IoHereDocument),
     /// Redirection from a here-string.
-    HereString(Option<u32>, Word),
+    HereString(Option<IoFd>, Word),
     /// Redirection of both standard output and standard error (with optional append).
     OutputAndError(Word, bool),
 }
@@ -909,6 +1062,27 @@ impl Display for IoFileRedirectKind {
     }
 }
 
+/// The file descriptor that is the subject of an I/O redirection.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub enum IoFd {
+    /// File descriptor number.
+    Number(u32),
+    /// Name of a variable that receives the number of a file descriptor allocated
+    /// by the shell (or, when closing, that holds the number of the descriptor).
+    Variable(String),
+}
+
+impl Display for IoFd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IoFd::Number(fd) => write!(f, "{}", fd),
+            IoFd::Variable(name) => write!(f, "{{{}}}", name),
+        }
+    }
+}
+
 /// Target for an I/O file redirection.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
@@ -918,6 +1092,10 @@ pub enum IoFileRedirectTarget {
     Filename(Word),
     /// File descriptor number.
     Fd(u32),
+    /// Target of a duplication redirection that's only known after expansion: a file
+    /// descriptor number, a file descriptor number followed by `-` (to move the
+    /// descriptor), `-` (to close the redirected descriptor), or a filename.
+    Duplicate(Word),
     /// Process substitution: substitution with the results of executing the given
     /// command in a subshell.
     ProcessSubstitution(ProcessSubstitutionKind, SubshellCommand),
@@ -928,6 +1106,7 @@ impl Display for IoFileRedirectTarget {
         match self {
             IoFileRedirectTarget::Filename(word) => write!(f, "{}", word),
             IoFileRedirectTarget::Fd(fd) => write!(f, "{}", fd),
+            IoFileRedirectTarget::Duplicate(word) => write!(f, "{}", word),
             IoFileRedirectTarget::ProcessSubstitution(kind, subshell_command) => {
                 write!(f, "{kind}{subshell_command}")
             }
@@ -1182,9 +1361,12 @@ impl Display for BinaryPredicate {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 #[cfg_attr(test, derive(PartialEq, Eq))]
+#[non_exhaustive]
 pub struct Word {
     /// Raw text of the word.
     pub value: String,
+    /// The position in its source at which the word starts, if it was parsed from one.
+    pub location: Option<tokenizer::SourcePosition>,
 }
 
 impl Display for Word {
@@ -1196,11 +1378,13 @@ impl Display for Word {
 impl From<&tokenizer::Token> for Word {
     fn from(t: &tokenizer::Token) -> Word {
         match t {
-            tokenizer::Token::Word(value, _) => Word {
+            tokenizer::Token::Word(value, location) => Word {
                 value: value.clone(),
+                location: Some(location.start.clone()),
             },
-            tokenizer::Token::Operator(value, _) => Word {
+            tokenizer::Token::Operator(value, location) => Word {
                 value: value.clone(),
+                location: Some(location.start.clone()),
             },
         }
     }
@@ -1208,7 +1392,10 @@ impl From<&tokenizer::Token> for Word {
 
 impl From<String> for Word {
     fn from(s: String) -> Word {
-        Word { value: s }
+        Word {
+            value: s,
+            location: None,
+        }
     }
 }
 
@@ -1217,6 +1404,16 @@ impl Word {
     pub fn new(s: &str) -> Self {
         Self {
             value: s.to_owned(),
+            location: None,
+        }
+    }
+
+    /// Constructs a new `Word` from a given string, found at the given position in
+    /// its source.
+    pub fn with_location(s: &str, location: tokenizer::SourcePosition) -> Self {
+        Self {
+            value: s.to_owned(),
+            location: Some(location),
         }
     }
 
@@ -37,6 +37,10 @@ pub enum WordParseError {
     #[error("failed to parse prompt string")]
     Prompt(peg::error::ParseError<peg::str::LineCol>),
 
+    /// An error occurred while parsing a brace expansion.
+    #[error("failed to parse brace expansion '{0}'")]
+    BraceExpansion(String, peg::error::ParseError<peg::str::LineCol>),
+
     /// An error occurred while parsing a parameter.
     #[error("failed to parse parameter '{0}'")]
     Parameter(String, peg::error::ParseError<peg::str::LineCol>),
@@ -1,6 +1,8 @@
 use crate::ast::{self, SeparatorOperator};
 use crate::error;
-use crate::tokenizer::{Token, TokenEndReason, Tokenizer, TokenizerOptions, Tokens};
+use crate::tokenizer::{
+    SourcePosition, Token, TokenEndReason, Tokenizer, TokenizerOptions, Tokens,
+};
 
 /// Options used to control the behavior of the parser.
 #[derive(Clone, Eq, Hash, PartialEq)]
@@ -67,6 +69,7 @@ impl<R: std::io::BufRead> Parser<R> {
                 posix_mode: self.options.posix_mode,
             },
         );
+        tokenizer.offset_line_numbers(self.source_info.line_offset);
 
         tracing::debug!(target: "tokenize", "Tokenizing...");
 
@@ -195,6 +198,9 @@ impl<'a> peg::ParseSlice<'a> for Tokens<'a> {
 pub struct SourceInfo {
     /// The source of the tokens.
     pub source: String,
+    /// The number of lines in the source that precede the tokens; line numbers
+    /// of parsed locations are offset by this amount.
+    pub line_offset: i32,
 }
 
 peg::parser! {
@@ -238,9 +244,25 @@ peg::parser! {
             specific_operator("||") { ast::AndOr::Or }
 
         rule pipeline() -> ast::Pipeline =
-            bang:bang()? seq:pipe_sequence() { ast::Pipeline { bang: bang.is_some(), seq } }
+            location:start_position() timed:pipeline_timed()? bang:bang()? seq:pipe_sequence() {
+                ast::Pipeline { timed, bang: bang.is_some(), seq, location }
+            } /
+            // N.B. A bare `time` times an empty pipeline.
+            location:start_position() timed:pipeline_timed() bang:bang()? &empty_pipeline_end() {
+                ast::Pipeline { timed: Some(timed), bang: bang.is_some(), seq: vec![], location }
+            }
         rule bang() -> bool = specific_word("!") { true }
 
+        rule empty_pipeline_end() -> () =
+            specific_operator(";") {} /
+            newline() /
+            ![_] {}
+
+        // N.B. Timing a pipeline is a non-sh extension.
+        rule pipeline_timed() -> ast::PipelineTimed =
+            non_posix_extensions_enabled() specific_word("time") specific_word("-p") { ast::PipelineTimed::TimedWithPosixOutput } /
+            non_posix_extensions_enabled() specific_word("time") { ast::PipelineTimed::Timed }
+
         pub(crate) rule pipe_sequence() -> Vec<ast::Command> =
             c:(c:command() r:&pipe_extension_redirection()? {? // check for `|&` without consuming the stream.
                 let mut c = c;
@@ -269,21 +291,25 @@ peg::parser! {
 
         // N.B. The arithmetic command is a non-sh extension.
         // N.B. The arithmetic for clause command is a non-sh extension.
+        // N.B. The select clause command is a non-sh extension.
+        // N.B. The coprocess command is a non-sh extension.
         pub(crate) rule compound_command() -> ast::CompoundCommand =
             non_posix_extensions_enabled() a:arithmetic_command() { ast::CompoundCommand::Arithmetic(a) } /
             b:brace_group() { ast::CompoundCommand::BraceGroup(b) } /
             s:subshell() { ast::CompoundCommand::Subshell(s) } /
             f:for_clause() { ast::CompoundCommand::ForClause(f) } /
+            non_posix_extensions_enabled() s:select_clause() { ast::CompoundCommand::SelectClause(s) } /
             c:case_clause() { ast::CompoundCommand::CaseClause(c) } /
             i:if_clause() { ast::CompoundCommand::IfClause(i) } /
             w:while_clause() { ast::CompoundCommand::WhileClause(w) } /
             u:until_clause() { ast::CompoundCommand::UntilClause(u) } /
             non_posix_extensions_enabled() c:arithmetic_for_clause() { ast::CompoundCommand::ArithmeticForClause(c) } /
+            non_posix_extensions_enabled() c:coproc_clause() { ast::CompoundCommand::Coprocess(c) } /
             expected!("compound command")
 
         pub(crate) rule arithmetic_command() -> ast::ArithmeticCommand =
-            specific_operator("(") specific_operator("(") expr:arithmetic_expression() specific_operator(")") specific_operator(")") {
-                ast::ArithmeticCommand { expr }
+            location:start_position() specific_operator("(") specific_operator("(") expr:arithmetic_expression() specific_operator(")") specific_operator(")") {
+                ast::ArithmeticCommand { expr, location }
             }
 
         pub(crate) rule arithmetic_expression() -> ast::UnexpandedArithmeticExpr =
@@ -299,7 +325,7 @@ peg::parser! {
             specific_operator(";") {}
 
         rule subshell() -> ast::SubshellCommand =
-            specific_operator("(") c:compound_list() specific_operator(")") { ast::SubshellCommand(c) }
+            location:start_position() specific_operator("(") c:compound_list() specific_operator(")") { ast::SubshellCommand(c, location) }
 
         rule compound_list() -> ast::CompoundList =
             linebreak() first:and_or() remainder:(s:separator() l:and_or() { (s, l) })* last_sep:separator()? {
@@ -324,15 +350,38 @@ peg::parser! {
             }
 
         rule for_clause() -> ast::ForClauseCommand =
-            specific_word("for") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
-                ast::ForClauseCommand { variable_name: n.to_owned(), values: w, body: d }
+            location:start_position() specific_word("for") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
+                ast::ForClauseCommand { variable_name: n.to_owned(), values: w, body: d, location }
+            } /
+            location:start_position() specific_word("for") n:name() sequential_sep()? d:do_group() {
+                ast::ForClauseCommand { variable_name: n.to_owned(), values: None, body: d, location }
+            }
+
+        // N.B. An `in` with no words that follow yields an empty menu rather than one
+        // made up of the positional parameters.
+        rule select_clause() -> ast::SelectClauseCommand =
+            location:start_position() specific_word("select") n:name() linebreak() _in() w:wordlist()? sequential_sep() d:do_group() {
+                ast::SelectClauseCommand { variable_name: n.to_owned(), values: Some(w.unwrap_or_default()), body: d, location }
+            } /
+            location:start_position() specific_word("select") n:name() sequential_sep()? d:do_group() {
+                ast::SelectClauseCommand { variable_name: n.to_owned(), values: None, body: d, location }
+            }
+
+        // N.B. A coprocess may only be given a name if its command is a compound command.
+        rule coproc_clause() -> ast::CoprocessCommand =
+            location:start_position() specific_word("coproc") n:name() c:compound_command() r:redirect_list()? {
+                ast::CoprocessCommand { name: Some(n.to_owned()), body: Box::new(ast::Command::Compound(c, r)), location }
+            } /
+            location:start_position() specific_word("coproc") c:compound_command() r:redirect_list()? {
+                ast::CoprocessCommand { name: None, body: Box::new(ast::Command::Compound(c, r)), location }
             } /
-            specific_word("for") n:name() sequential_sep()? d:do_group() {
-                ast::ForClauseCommand { variable_name: n.to_owned(), values: None, body: d }
+            location:start_position() specific_word("coproc") c:simple_command() {
+                ast::CoprocessCommand { name: None, body: Box::new(ast::Command::Simple(c)), location }
             }
 
         // N.B. The arithmetic for loop is a non-sh extension.
         rule arithmetic_for_clause() -> ast::ArithmeticForClauseCommand =
+            location:start_position()
             specific_word("for")
             specific_operator("(") specific_operator("(")
                 initializer:arithmetic_expression()? specific_operator(";")
@@ -341,7 +390,7 @@ peg::parser! {
             specific_operator(")") specific_operator(")")
             sequential_sep()
             body:do_group() {
-                ast::ArithmeticForClauseCommand { initializer, condition, updater, body }
+                ast::ArithmeticForClauseCommand { initializer, condition, updater, body, location }
             }
 
         rule extended_test_command() -> ast::ExtendedTestExpr =
@@ -414,8 +463,8 @@ peg::parser! {
         // N.B. For some reason we seem to need to allow a select subset
         // of unescaped operators in regex words.
         rule regex_word() -> ast::Word =
-            value:$((!specific_word("]]") regex_word_piece())+) {
-                ast::Word { value }
+            location:start_position() value:$((!specific_word("]]") regex_word_piece())+) {
+                ast::Word { value, location: Some(location) }
             }
 
         rule regex_word_piece() =
@@ -439,14 +488,14 @@ peg::parser! {
 
         // TODO: validate if this should call non_reserved_word() or word()
         pub(crate) rule case_clause() -> ast::CaseClauseCommand =
-            specific_word("case") w:non_reserved_word() linebreak() _in() linebreak() first_items:case_item()* last_item:case_item_ns()? specific_word("esac") {
+            location:start_position() specific_word("case") w:non_reserved_word() linebreak() _in() linebreak() first_items:case_item()* last_item:case_item_ns()? specific_word("esac") {
                 let mut cases = first_items;
 
                 if let Some(last_item) = last_item {
                     cases.push(last_item);
                 }
 
-                ast::CaseClauseCommand { value: ast::Word::from(w), cases }
+                ast::CaseClauseCommand { value: ast::Word::from(w), cases, location }
             }
 
         pub(crate) rule case_item_ns() -> ast::CaseItem =
@@ -481,11 +530,12 @@ peg::parser! {
             (w:word() { ast::Word::from(w) }) ++ specific_operator("|")
 
         rule if_clause() -> ast::IfClauseCommand =
-            specific_word("if") condition:compound_list() specific_word("then") then:compound_list() elses:else_part()? specific_word("fi") {
+            location:start_position() specific_word("if") condition:compound_list() specific_word("then") then:compound_list() elses:else_part()? specific_word("fi") {
                 ast::IfClauseCommand {
                     condition,
                     then,
                     elses,
+                    location,
                 }
             }
 
@@ -515,18 +565,18 @@ peg::parser! {
              }
 
         rule while_clause() -> ast::WhileOrUntilClauseCommand =
-            specific_word("while") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d) }
+            location:start_position() specific_word("while") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d, location) }
 
         rule until_clause() -> ast::WhileOrUntilClauseCommand =
-            specific_word("until") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d) }
+            location:start_position() specific_word("until") c:compound_list() d:do_group() { ast::WhileOrUntilClauseCommand(c, d, location) }
 
         // N.B. Non-sh extensions allows use of the 'function' word to indicate a function definition.
         rule function_definition() -> ast::FunctionDefinition =
-            specific_word("function")? fname:fname() specific_operator("(") specific_operator(")") linebreak() body:function_body() {
-                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone() }
+            location:start_position() specific_word("function")? fname:fname() specific_operator("(") specific_operator(")") linebreak() body:function_body() {
+                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone(), location }
             } /
-            specific_word("function") fname:fname() linebreak() body:function_body() {
-                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone() }
+            location:start_position() specific_word("function") fname:fname() linebreak() body:function_body() {
+                ast::FunctionDefinition { fname: fname.to_owned(), body, source: source_info.source.clone(), location }
             } /
             expected!("function definition")
 
@@ -540,24 +590,24 @@ peg::parser! {
             w:[Token::Word(word, _) if !word.ends_with('=')] { w.to_str() }
 
         rule brace_group() -> ast::BraceGroupCommand =
-            specific_word("{") c:compound_list() specific_word("}") { ast::BraceGroupCommand(c) }
+            location:start_position() specific_word("{") c:compound_list() specific_word("}") { ast::BraceGroupCommand(c, location) }
 
         rule do_group() -> ast::DoGroupCommand =
             specific_word("do") c:compound_list() specific_word("done") { ast::DoGroupCommand(c) }
 
         rule simple_command() -> ast::SimpleCommand =
-            prefix:cmd_prefix() word_and_suffix:(word_or_name:cmd_word() suffix:cmd_suffix()? { (word_or_name, suffix) })? {
+            location:start_position() prefix:cmd_prefix() word_and_suffix:(word_or_name:cmd_word() suffix:cmd_suffix()? { (word_or_name, suffix) })? {
                 match word_and_suffix {
                     Some((word_or_name, suffix)) => {
-                        ast::SimpleCommand { prefix: Some(prefix), word_or_name: Some(ast::Word::from(word_or_name)), suffix }
+                        ast::SimpleCommand { prefix: Some(prefix), word_or_name: Some(ast::Word::from(word_or_name)), suffix, location }
                     }
                     None => {
-                        ast::SimpleCommand { prefix: Some(prefix), word_or_name: None, suffix: None }
+                        ast::SimpleCommand { prefix: Some(prefix), word_or_name: None, suffix: None, location }
                     }
                 }
             } /
-            word_or_name:cmd_name() suffix:cmd_suffix()? {
-                ast::SimpleCommand { prefix: None, word_or_name: Some(ast::Word::from(word_or_name)), suffix } } /
+            location:start_position() word_or_name:cmd_name() suffix:cmd_suffix()? {
+                ast::SimpleCommand { prefix: None, word_or_name: Some(ast::Word::from(word_or_name)), suffix, location } } /
             expected!("simple command")
 
         rule cmd_name() -> &'input Token =
@@ -599,16 +649,21 @@ peg::parser! {
 
         // N.B. here strings are extensions to the POSIX standard.
         rule io_redirect() -> ast::IoRedirect =
-            n:io_number()? f:io_file() {
+            n:io_redirect_fd()? f:io_file() {
                     let (kind, target) = f;
                     ast::IoRedirect::File(n, kind, target)
                 } /
             non_posix_extensions_enabled() specific_operator("&>>") target:filename() { ast::IoRedirect::OutputAndError(ast::Word::from(target), true) } /
             non_posix_extensions_enabled() specific_operator("&>") target:filename() { ast::IoRedirect::OutputAndError(ast::Word::from(target), false) } /
-            non_posix_extensions_enabled() n:io_number()? specific_operator("<<<") w:word() { ast::IoRedirect::HereString(n, ast::Word::from(w)) } /
-            n:io_number()? h:io_here() { ast::IoRedirect::HereDocument(n, h) } /
+            non_posix_extensions_enabled() n:io_redirect_fd()? specific_operator("<<<") w:word() { ast::IoRedirect::HereString(n, ast::Word::from(w)) } /
+            n:io_redirect_fd()? h:io_here() { ast::IoRedirect::HereDocument(n, h) } /
             expected!("I/O redirect")
 
+        // N.B. Redirecting a shell-allocated fd named by a variable (e.g., `{fd}>file`) is a non-sh extension.
+        rule io_redirect_fd() -> ast::IoFd =
+            n:io_number() { ast::IoFd::Number(n) } /
+            non_posix_extensions_enabled() v:io_fd_variable() { ast::IoFd::Variable(v.to_owned()) }
+
         // N.B. Process substitution forms are extensions to the POSIX standard.
         rule io_file() -> (ast::IoFileRedirectKind, ast::IoFileRedirectTarget) =
             specific_operator("<")  f:io_filename() { (ast::IoFileRedirectKind::Read, f) } /
@@ -621,6 +676,7 @@ peg::parser! {
 
         rule io_filename_or_fd() -> ast::IoFileRedirectTarget =
             fd:io_fd() { ast::IoFileRedirectTarget::Fd(fd) } /
+            w:filename() { ast::IoFileRedirectTarget::Duplicate(ast::Word::from(w)) } /
             io_filename()
 
         rule io_fd() -> u32 =
@@ -720,14 +776,16 @@ peg::parser! {
             specific_word("[[") /
             specific_word("]]") /
             specific_word("function") /
-            specific_word("select")
+            specific_word("select") /
+            specific_word("coproc") /
+            specific_word("time")
 
         rule newline() -> () = quiet! {
             specific_operator("\n") {}
         }
 
         pub(crate) rule assignment_word() -> (ast::Assignment, ast::Word) =
-            non_posix_extensions_enabled() [Token::Word(w, _)] specific_operator("(") elements:array_elements() specific_operator(")") {?
+            non_posix_extensions_enabled() [Token::Word(w, l)] specific_operator("(") elements:array_elements() specific_operator(")") {?
                 let parsed = parse_array_assignment(w.as_str(), elements.as_slice())?;
 
                 let mut all_as_word = w.to_owned();
@@ -740,11 +798,11 @@ peg::parser! {
                 }
                 all_as_word.push(')');
 
-                Ok((parsed, ast::Word { value: all_as_word }))
+                Ok((parsed, ast::Word { value: all_as_word, location: Some(l.start.clone()) }))
             } /
-            [Token::Word(w, _)] {?
+            [Token::Word(w, l)] {?
                 let parsed = parse_assignment_word(w.as_str())?;
-                Ok((parsed, ast::Word { value: w.to_owned() }))
+                Ok((parsed, ast::Word { value: w.to_owned(), location: Some(l.start.clone()) }))
             }
 
         rule array_elements() -> Vec<&'input String> =
@@ -755,6 +813,16 @@ peg::parser! {
 
         // N.B. An I/O number must be a string of only digits, and it must be
         // followed by a '<' or '>' character (but not consume them).
+        // N.B. The variable name must be immediately followed by the redirection operator.
+        rule io_fd_variable() -> &'input str =
+            w:[Token::Word(_, _)] op:&([Token::Operator(o, _) if o.starts_with('<') || o.starts_with('>')]) {?
+                let s = w.to_str();
+                match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) if valid_variable_name(name) && w.location().end.index == op.location().start.index => Ok(name),
+                    _ => Err("io_fd_variable"),
+                }
+            }
+
         rule io_number() -> u32 =
             [Token::Word(w, _) if w.chars().all(|c: char| c.is_ascii_digit())]
             &([Token::Operator(o, _) if o.starts_with('<') || o.starts_with('>')]) {
@@ -770,6 +838,10 @@ peg::parser! {
         rule specific_word(expected: &str) -> &'input Token =
             [Token::Word(w, _) if w.as_str() == expected]
 
+        // Matches the next token without consuming it, yielding where it starts.
+        rule start_position() -> SourcePosition =
+            t:&[_] { t.location().start.clone() }
+
         rule non_posix_extensions_enabled() -> () =
             &[_] {? if !parser_options.sh_mode { Ok(()) } else { Err("posix") } }
     }
@@ -821,7 +893,7 @@ peg::parser! {
             ['_' | 'a'..='z' | 'A'..='Z'] {}
 
         rule scalar_value() -> ast::AssignmentValue =
-            v:$([_]*) { ast::AssignmentValue::Scalar(ast::Word { value: v.to_owned() }) }
+            v:$([_]*) { ast::AssignmentValue::Scalar(ast::Word::new(v)) }
     }
 }
 
@@ -830,10 +902,20 @@ fn parse_assignment_word(word: &str) -> Result<ast::Assignment, &'static str> {
     parse_result.map_err(|_| "not assignment word")
 }
 
+fn valid_variable_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
 // add `2>&1` to the command if the pipeline is `|&`
 fn add_pipe_extension_redirection(c: &mut ast::Command) -> Result<(), &'static str> {
     let r = ast::IoRedirect::File(
-        Some(2),
+        Some(ast::IoFd::Number(2)),
         ast::IoFileRedirectKind::DuplicateOutput,
         ast::IoFileRedirectTarget::Fd(1),
     );
@@ -972,7 +1054,7 @@ esac\
             assert_matches!(
                 c.0[0],
                 ast::CommandPrefixOrSuffixItem::IoRedirect(ast::IoRedirect::File(
-                    Some(2),
+                    Some(ast::IoFd::Number(2)),
                     ast::IoFileRedirectKind::DuplicateOutput,
                     ast::IoFileRedirectTarget::Fd(1)
                 ))
@@ -1002,7 +1084,7 @@ esac\
                 assert_matches!(
                     l.as_ref().unwrap().0[0],
                     ast::IoRedirect::File(
-                        Some(2),
+                        Some(ast::IoFd::Number(2)),
                         ast::IoFileRedirectKind::DuplicateOutput,
                         ast::IoFileRedirectTarget::Fd(1)
                     )
@@ -1027,25 +1109,52 @@ for f in A B C; do
 
 "#;
         use ast::*;
+        let word_at = |value: &str, index, line, column| Word {
+            value: value.to_owned(),
+            location: Some(SourcePosition {
+                index,
+                line,
+                column,
+            }),
+        };
         let expected = Program {
             complete_commands: vec![CompoundList(vec![CompoundListItem(
                 AndOrList {
                     first: Pipeline {
+                        timed: None,
                         bang: false,
+                        location: SourcePosition {
+                            index: 23,
+                            line: 5,
+                            column: 1,
+                        },
                         seq: vec![Command::Compound(
                             CompoundCommand::ForClause(ForClauseCommand {
                                 variable_name: "f".into(),
-                                values: Some(vec![Word::new("A"), Word::new("B"), Word::new("C")]),
+                                values: Some(vec![
+                                    word_at("A", 32, 5, 10),
+                                    word_at("B", 34, 5, 12),
+                                    word_at("C", 36, 5, 14),
+                                ]),
                                 body: DoGroupCommand(CompoundList(vec![CompoundListItem(
                                     AndOrList {
                                         first: Pipeline {
+                                            timed: None,
                                             bang: false,
+                                            location: SourcePosition {
+                                                index: 60,
+                                                line: 8,
+                                                column: 5,
+                                            },
                                             seq: vec![Command::Simple(SimpleCommand {
                                                 prefix: None,
-                                                word_or_name: Some(Word::new("echo")),
+                                                word_or_name: Some(word_at("echo", 60, 8, 5)),
                                                 suffix: Some(CommandSuffix(vec![
-                                                    CommandPrefixOrSuffixItem::Word(Word::new(
+                                                    CommandPrefixOrSuffixItem::Word(word_at(
                                                         r#""${f@L}""#,
+                                                        65,
+                                                        8,
+                                                        10,
                                                     )),
                                                     CommandPrefixOrSuffixItem::IoRedirect(
                                                         IoRedirect::File(
@@ -1055,12 +1164,22 @@ for f in A B C; do
                                                         ),
                                                     ),
                                                 ])),
+                                                location: SourcePosition {
+                                                    index: 60,
+                                                    line: 8,
+                                                    column: 5,
+                                                },
                                             })],
                                         },
                                         additional: vec![],
                                     },
                                     SeparatorOperator::Sequence,
                                 )])),
+                                location: SourcePosition {
+                                    index: 23,
+                                    line: 5,
+                                    column: 1,
+                                },
                             }),
                             None,
                         )],
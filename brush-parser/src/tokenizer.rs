@@ -28,7 +28,7 @@ pub(crate) enum TokenEndReason {
 }
 
 /// Represents a position in a source shell script.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 pub struct SourcePosition {
     /// The 0-based index of the character in the input stream.
@@ -46,7 +46,7 @@ impl Display for SourcePosition {
 }
 
 /// Represents the location of a token in its source shell script.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "fuzz-testing", derive(arbitrary::Arbitrary))]
 pub struct TokenLocation {
     /// The start position of the token.
@@ -508,6 +508,15 @@ impl<'a, R: ?Sized + std::io::BufRead> Tokenizer<'a, R> {
         }
     }
 
+    /// Offsets the line numbers tracked for input that has yet to be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_offset` - The number of lines to offset by.
+    pub fn offset_line_numbers(&mut self, line_offset: i32) {
+        self.cross_state.cursor.line += line_offset;
+    }
+
     pub fn current_location(&self) -> Option<SourcePosition> {
         Some(self.cross_state.cursor.clone())
     }
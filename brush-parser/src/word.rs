@@ -8,6 +8,7 @@
 //! - Parameter expansion expressions.
 //! - Command substitution expressions.
 //! - Arithmetic expansion expressions.
+//! - Brace expansion expressions.
 
 use crate::ast;
 use crate::error;
@@ -363,6 +364,126 @@ pub enum ParameterTransformOp {
     ToUpperCase,
 }
 
+/// Represents a piece of a word that may contain brace expressions.
+#[derive(Clone, Debug)]
+pub enum BraceExpressionOrText {
+    /// A brace expression.
+    Expr(BraceExpression),
+    /// Text that is not subject to brace expansion.
+    Text(String),
+}
+
+/// A brace expression, consisting of the members enclosed by the braces.
+pub type BraceExpression = Vec<BraceExpressionMember>;
+
+/// A member of a brace expression.
+#[derive(Clone, Debug)]
+pub enum BraceExpressionMember {
+    /// A sequence of integers.
+    NumberSequence {
+        /// Start of the sequence.
+        low: i64,
+        /// End of the sequence (inclusive).
+        high: i64,
+        /// Increment between members of the sequence.
+        increment: i64,
+        /// Minimum width of each generated number; numbers shorter than
+        /// this width are padded with leading zeroes.
+        width: usize,
+    },
+    /// A sequence of characters.
+    CharSequence {
+        /// Start of the sequence.
+        low: char,
+        /// End of the sequence (inclusive).
+        high: char,
+        /// Increment between members of the sequence.
+        increment: i64,
+    },
+    /// A sequence of text and (possibly nested) brace expressions.
+    Child(Vec<BraceExpressionOrText>),
+}
+
+impl BraceExpressionOrText {
+    /// Generates the expansions of this piece.
+    pub fn generate(self) -> Vec<String> {
+        match self {
+            BraceExpressionOrText::Expr(members) => members
+                .into_iter()
+                .flat_map(BraceExpressionMember::generate)
+                .collect(),
+            BraceExpressionOrText::Text(text) => vec![text],
+        }
+    }
+}
+
+impl BraceExpressionMember {
+    /// Generates the expansions of this member.
+    pub fn generate(self) -> Vec<String> {
+        match self {
+            BraceExpressionMember::NumberSequence {
+                low,
+                high,
+                increment,
+                width,
+            } => generate_sequence(low, high, increment)
+                .map(|n| std::format!("{n:0width$}"))
+                .collect(),
+            BraceExpressionMember::CharSequence {
+                low,
+                high,
+                increment,
+            } => generate_sequence(u32::from(low).into(), u32::from(high).into(), increment)
+                .filter_map(|n| u32::try_from(n).ok().and_then(char::from_u32))
+                .map(String::from)
+                .collect(),
+            BraceExpressionMember::Child(pieces) => generate_brace_expansions(pieces),
+        }
+    }
+}
+
+/// Generates the words resulting from brace-expanding the given sequence of pieces.
+///
+/// # Arguments
+///
+/// * `pieces` - The pieces to expand.
+pub fn generate_brace_expansions(pieces: Vec<BraceExpressionOrText>) -> Vec<String> {
+    let mut results = vec![String::new()];
+
+    for piece in pieces {
+        let expansions = piece.generate();
+        results = results
+            .iter()
+            .flat_map(|prefix| {
+                expansions
+                    .iter()
+                    .map(move |expansion| std::format!("{prefix}{expansion}"))
+            })
+            .collect();
+    }
+
+    results
+}
+
+fn generate_sequence(low: i64, high: i64, increment: i64) -> Box<dyn Iterator<Item = i64>> {
+    // N.B. The sign of the increment is ignored; the direction of the sequence is
+    // determined by the relative order of its endpoints. A zero increment is treated as 1.
+    let step = usize::try_from(increment.unsigned_abs())
+        .unwrap_or(usize::MAX)
+        .max(1);
+
+    if low <= high {
+        Box::new((low..=high).step_by(step))
+    } else {
+        Box::new((high..=low).rev().step_by(step))
+    }
+}
+
+fn has_leading_zero(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    digits.len() > 1 && digits.starts_with('0')
+}
+
 /// Parse a word into its constituent pieces.
 ///
 /// # Arguments
@@ -405,6 +526,30 @@ pub fn parse_parameter(
         .map_err(|err| error::WordParseError::Parameter(word.to_owned(), err))
 }
 
+/// Parse the given word into a sequence of text and brace expressions. Returns `None`
+/// if the word contains no brace expressions and therefore requires no brace expansion.
+///
+/// # Arguments
+///
+/// * `word` - The word to parse.
+/// * `options` - The parser options to use.
+pub fn parse_brace_expansions(
+    word: &str,
+    options: &ParserOptions,
+) -> Result<Option<Vec<BraceExpressionOrText>>, error::WordParseError> {
+    let pieces = expansion_parser::brace_expansions(word, options)
+        .map_err(|err| error::WordParseError::BraceExpansion(word.to_owned(), err))?;
+
+    if pieces
+        .iter()
+        .any(|piece| matches!(piece, BraceExpressionOrText::Expr(_)))
+    {
+        Ok(Some(pieces))
+    } else {
+        Ok(None)
+    }
+}
+
 peg::parser! {
     grammar expansion_parser(parser_options: &ParserOptions) for str {
         pub(crate) rule unexpanded_word() -> Vec<WordPieceWithSource> = word(<![_]>)
@@ -711,6 +856,80 @@ peg::parser! {
         rule parameter_expression_word() -> String =
             s:$(word(<['}']>)) { s.to_owned() }
 
+        pub(crate) rule brace_expansions() -> Vec<BraceExpressionOrText> =
+            brace_expansion_piece(<![_]>)*
+
+        rule brace_expansion_piece<T>(stop_condition: rule<T>) -> BraceExpressionOrText =
+            expr:brace_expr() { BraceExpressionOrText::Expr(expr) } /
+            text:$(brace_text_piece(<stop_condition()>)+) { BraceExpressionOrText::Text(text.to_owned()) }
+
+        rule brace_text_piece<T>(stop_condition: rule<T>) =
+            !stop_condition() !brace_expr() brace_text_atom()
+
+        // N.B. Quoted text, escape sequences, and expansions introduced by '$' or '`' are
+        // never subject to brace expansion, so we consume them whole.
+        rule brace_text_atom() =
+            "\\" [_] {} /
+            "$"? "\'" [^'\'']* "\'" {} /
+            "\"" ("\\" [_] / "$" brace_balanced_group() / "`" [^'`']* "`" / [^'\"'])* "\"" {} /
+            "$" brace_balanced_group() {} /
+            "`" [^'`']* "`" {} /
+            [_] {}
+
+        rule brace_balanced_group() =
+            "{" ("\\" [_] / "\'" [^'\'']* "\'" / brace_balanced_group() / [^'}'])* "}" {} /
+            "(" ("\\" [_] / "\'" [^'\'']* "\'" / brace_balanced_group() / [^')'])* ")" {}
+
+        rule brace_expr() -> BraceExpression =
+            "{" seq:brace_sequence() "}" { vec![seq] } /
+            "{" members:(brace_member() ++ ",") "}" {?
+                if members.len() > 1 { Ok(members) } else { Err("brace expression") }
+            }
+
+        rule brace_member() -> BraceExpressionMember =
+            pieces:brace_member_piece()* {
+                BraceExpressionMember::Child(pieces.into_iter().flatten().collect())
+            }
+
+        // N.B. Braces within a member that don't form a valid brace expression are kept as
+        // literal text; they must still be balanced so that they don't close the enclosing
+        // expression.
+        rule brace_member_piece() -> Vec<BraceExpressionOrText> =
+            expr:brace_expr() { vec![BraceExpressionOrText::Expr(expr)] } /
+            "{" inner:brace_literal_group_piece()* "}" {
+                let mut pieces = vec![BraceExpressionOrText::Text("{".to_owned())];
+                pieces.extend(inner.into_iter().flatten());
+                pieces.push(BraceExpressionOrText::Text("}".to_owned()));
+                pieces
+            } /
+            text:$(brace_text_piece(<[',' | '{' | '}']>)+) { vec![BraceExpressionOrText::Text(text.to_owned())] }
+
+        rule brace_literal_group_piece() -> Vec<BraceExpressionOrText> =
+            brace_member_piece() /
+            "," { vec![BraceExpressionOrText::Text(",".to_owned())] }
+
+        rule brace_sequence() -> BraceExpressionMember =
+            low:brace_sequence_number() ".." high:brace_sequence_number() increment:brace_sequence_increment()? {
+                let (low, low_str) = low;
+                let (high, high_str) = high;
+                let width = if has_leading_zero(low_str) || has_leading_zero(high_str) {
+                    std::cmp::max(low_str.len(), high_str.len())
+                } else {
+                    0
+                };
+
+                BraceExpressionMember::NumberSequence { low, high, increment: increment.unwrap_or(1), width }
+            } /
+            low:[c if c.is_ascii_alphabetic()] ".." high:[c if c.is_ascii_alphabetic()] increment:brace_sequence_increment()? {
+                BraceExpressionMember::CharSequence { low, high, increment: increment.unwrap_or(1) }
+            }
+
+        rule brace_sequence_increment() -> i64 =
+            ".." n:brace_sequence_number() { n.0 }
+
+        rule brace_sequence_number() -> (i64, &'input str) =
+            s:$("-"? ['0'..='9']+) {? s.parse().map(|n| (n, s)).or(Err("i64")) }
+
         rule extglob_enabled() -> () =
             &[_] {? if parser_options.enable_extended_globbing { Ok(()) } else { Err("no extglob") } }
 
@@ -774,6 +993,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_brace_expansions() -> Result<()> {
+        let expand = |word: &str| -> Result<Vec<String>> {
+            let pieces = super::parse_brace_expansions(word, &ParserOptions::default())?;
+            Ok(pieces.map_or_else(|| vec![word.to_owned()], super::generate_brace_expansions))
+        };
+
+        assert_eq!(expand("{a,b,c}")?, vec!["a", "b", "c"]);
+        assert_eq!(expand("x{a,b{1,2}}y")?, vec!["xay", "xb1y", "xb2y"]);
+        assert_eq!(expand("{1..10..3}")?, vec!["1", "4", "7", "10"]);
+        assert_eq!(expand("{3..1}")?, vec!["3", "2", "1"]);
+        assert_eq!(expand("{08..11}")?, vec!["08", "09", "10", "11"]);
+        assert_eq!(expand("{a..e..2}")?, vec!["a", "c", "e"]);
+        assert_eq!(expand("{a}")?, vec!["{a}"]);
+        assert_eq!(expand("'{a,b}'")?, vec!["'{a,b}'"]);
+        assert_eq!(expand("${x,y}")?, vec!["${x,y}"]);
+        assert_eq!(expand("{a,{b},c}")?, vec!["a", "{b}", "c"]);
+        assert_eq!(expand("{a,{b{1,2}},c}")?, vec!["a", "{b1}", "{b2}", "c"]);
+        assert_eq!(expand("x{a,{},c}y")?, vec!["xay", "x{}y", "xcy"]);
+        assert_eq!(expand("{{a},b}")?, vec!["{a}", "b"]);
+        assert_eq!(expand("{a,{b,c}")?, vec!["{a,b", "{a,c"]);
+
+        Ok(())
+    }
+
     #[test]
     fn parse_extglob_with_embedded_parameter() -> Result<()> {
         let parsed = super::parse("+([$var])", &ParserOptions::default())?;
@@ -140,6 +140,36 @@ async fn run_impl(
     let mut shell = instantiate_shell(&args, cli_args, factory).await?;
 
     // Handle commands.
+    let run_result = run_input(&mut shell, args).await;
+
+    // Run any registered EXIT trap handler; this needs to happen even if we're exiting due
+    // to an error.
+    let params = shell.shell().as_ref().default_exec_params();
+    shell
+        .shell_mut()
+        .as_mut()
+        .run_exit_trap_handler(&params)
+        .await?;
+
+    run_result?;
+
+    // Make sure to return the last result observed in the shell.
+    let result = shell.shell().as_ref().last_result();
+
+    Ok(result)
+}
+
+/// Runs the input requested by the command-line arguments: a command string, a script, or
+/// commands read from stdin.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to run the input in.
+/// * `args` - The already-parsed command-line arguments.
+async fn run_input(
+    shell: &mut impl brush_interactive::InteractiveShell,
+    args: CommandLineArgs,
+) -> Result<(), brush_interactive::ShellError> {
     if let Some(command) = args.command {
         // Pass through args.
         if let Some(script_path) = args.script_path {
@@ -170,10 +200,7 @@ async fn run_impl(
         shell.run_interactively().await?;
     }
 
-    // Make sure to return the last result observed in the shell.
-    let result = shell.shell().as_ref().last_result();
-
-    Ok(result)
+    Ok(())
 }
 
 async fn instantiate_shell(
@@ -116,6 +116,26 @@ fn run_pipeline_interactively() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn run_read_with_initial_text() -> anyhow::Result<()> {
+    let mut session = start_shell_session()?;
+
+    // Read a line that starts out with some initial text.
+    session.expect_prompt()?;
+    session.send_line("read -e -i start- x")?;
+    session.send_line("typed")?;
+    session.expect_prompt()?;
+
+    // Make sure the typed text was appended to the initial text.
+    let output = session.exec_output(r#"echo "[$x]""#)?;
+    assert!(output.contains("[start-typed]"));
+
+    // Exit the shell.
+    session.exit()?;
+
+    Ok(())
+}
+
 //
 // Helpers
 //